@@ -0,0 +1,289 @@
+//! Provider for Azure OpenAI deployments
+//!
+//! Azure OpenAI speaks the OpenAI chat completions API, so requests are
+//! passed through mostly as received. The model is addressed by the
+//! `[backend] deployment` in the URL, every request carries the
+//! `api_version` as the `api-version` query parameter, and the
+//! `[backend.auth]` token is sent in an `api-key` header instead of a bearer
+//! token. `endpoint` is the resource URL, such as
+//! `https://my-resource.openai.azure.com`. Requests for several completions
+//! are forwarded with `n`, and every choice in the reply is returned.
+
+use std::error::Error;
+
+use futures::future::BoxFuture;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde_json::{json, Value};
+
+use crate::config::BackendConfig;
+use crate::openai::{ChatCompletionRequest, EmbeddingRequest, EmbeddingResponse};
+use crate::provider::{
+    extract_replies, extract_reply, forward_embeddings, resolve_system_prompt, validate_choices,
+    AppError, BackendReply,
+};
+use crate::registry::Provider;
+use crate::state::Snapshot;
+
+/// Header carrying the Azure OpenAI key
+const API_KEY_HEADER: &str = "api-key";
+
+/// Provider for Azure OpenAI deployments
+#[derive(Debug)]
+pub struct AzureOpenAiProvider;
+
+impl Provider for AzureOpenAiProvider {
+    fn name(&self) -> &'static str {
+        "azure_openai"
+    }
+
+    fn transform_request(&self, request: &ChatCompletionRequest, backend: &BackendConfig) -> Value {
+        transform_request(request, backend)
+    }
+
+    fn validate_response(
+        &self,
+        backend_response: &Value,
+        _backend: &BackendConfig,
+    ) -> Result<(), AppError> {
+        validate_choices(backend_response)
+    }
+
+    fn extract_reply(
+        &self,
+        backend_response: &Value,
+        backend: &BackendConfig,
+    ) -> Result<BackendReply, AppError> {
+        extract_reply(backend_response, &backend.mapping)
+    }
+
+    fn extract_replies(
+        &self,
+        backend_response: &Value,
+        backend: &BackendConfig,
+    ) -> Result<Vec<BackendReply>, AppError> {
+        extract_replies(backend_response, &backend.mapping)
+    }
+
+    fn supports_n(&self) -> bool {
+        true
+    }
+
+    fn validate(&self, backend: &BackendConfig) -> Result<(), String> {
+        for (key, value) in [
+            ("deployment", &backend.deployment),
+            ("api_version", &backend.api_version),
+        ] {
+            match value.as_deref() {
+                None | Some("") => {
+                    return Err(format!(
+                        "Invalid [backend] configuration: {} is required by the azure_openai provider",
+                        key
+                    ))
+                }
+                // Both end up in the URL unescaped
+                Some(value) if !value.chars().all(is_url_safe) => {
+                    return Err(format!(
+                        "Invalid [backend] configuration: {} {:?} may only contain letters, digits, '-', '_' and '.'",
+                        key, value
+                    ))
+                }
+                Some(_) => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn completions_url(&self, endpoint: &str, backend: &BackendConfig) -> String {
+        format!(
+            "{}/openai/deployments/{}/chat/completions?api-version={}",
+            endpoint.trim_end_matches('/'),
+            backend.deployment.as_deref().unwrap_or_default(),
+            backend.api_version.as_deref().unwrap_or_default()
+        )
+    }
+
+    fn embed<'a>(
+        &'a self,
+        snapshot: &'a Snapshot,
+        request: EmbeddingRequest,
+        request_id: &'a str,
+    ) -> BoxFuture<'a, Result<EmbeddingResponse, AppError>> {
+        Box::pin(forward_embeddings(snapshot, request, request_id))
+    }
+
+    fn token_headers(&self, token: &str) -> Result<HeaderMap, Box<dyn Error>> {
+        if token.is_empty() {
+            return Err("Azure OpenAI API key is empty".into());
+        }
+
+        let mut value = HeaderValue::from_str(token)
+            .map_err(|_| "Azure OpenAI API key contains invalid characters")?;
+        value.set_sensitive(true);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(HeaderName::from_static(API_KEY_HEADER), value);
+        Ok(headers)
+    }
+}
+
+/// Whether `c` can appear in a deployment name or API version
+fn is_url_safe(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')
+}
+
+/// Build the OpenAI payload sent to the deployment
+/// Unset parameters are omitted, the response is always requested in one
+/// piece since the proxy streams it itself, and a configured system prompt
+/// replaces the client's system messages as a single leading one.
+fn transform_request(openai_req: &ChatCompletionRequest, backend: &BackendConfig) -> Value {
+    let mut request = serde_json::to_value(openai_req).unwrap_or_else(|_| json!({}));
+    if let Some(object) = request.as_object_mut() {
+        object.retain(|_, value| !value.is_null());
+        object.remove("stream");
+        object.remove("stream_options");
+
+        if let Some(system_prompt) = resolve_system_prompt(openai_req, backend) {
+            let mut messages = vec![json!({"role": "system", "content": system_prompt})];
+            messages.extend(
+                openai_req
+                    .messages
+                    .iter()
+                    .filter(|m| m.role != "system")
+                    .map(|m| json!(m)),
+            );
+            object.insert("messages".to_string(), Value::Array(messages));
+        }
+    }
+    request
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::AUTHORIZATION;
+
+    fn backend(extra: &str) -> BackendConfig {
+        toml::from_str(&format!(
+            r#"
+            provider = "azure_openai"
+            endpoint = "https://my-resource.openai.azure.com/"
+            {}
+
+            [auth]
+            token = "azure-key"
+        "#,
+            extra
+        ))
+        .unwrap()
+    }
+
+    fn request(value: Value) -> ChatCompletionRequest {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_completions_url_names_deployment_and_api_version() {
+        let backend = backend("deployment = \"gpt-4o\"\napi_version = \"2024-06-01\"");
+
+        assert!(AzureOpenAiProvider.validate(&backend).is_ok());
+        assert_eq!(
+            AzureOpenAiProvider.completions_url(&backend.endpoint, &backend),
+            "https://my-resource.openai.azure.com/openai/deployments/gpt-4o/chat/completions?api-version=2024-06-01"
+        );
+    }
+
+    #[test]
+    fn test_validate_requires_deployment_and_api_version() {
+        for (extra, expected) in [
+            ("api_version = \"2024-06-01\"", "deployment is required"),
+            ("deployment = \"gpt-4o\"", "api_version is required"),
+            (
+                "deployment = \"gpt-4o/../x\"\napi_version = \"2024-06-01\"",
+                "deployment \"gpt-4o/../x\" may only contain",
+            ),
+            (
+                "deployment = \"gpt-4o\"\napi_version = \"1&x=y\"",
+                "api_version \"1&x=y\" may only contain",
+            ),
+        ] {
+            let err = AzureOpenAiProvider.validate(&backend(extra)).unwrap_err();
+            assert!(err.contains(expected), "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_token_is_sent_as_api_key() {
+        let headers = AzureOpenAiProvider.token_headers("azure-key").unwrap();
+
+        assert_eq!(headers[API_KEY_HEADER], "azure-key");
+        assert!(headers[API_KEY_HEADER].is_sensitive());
+        assert!(!headers.contains_key(AUTHORIZATION));
+        assert!(AzureOpenAiProvider.token_headers("").is_err());
+    }
+
+    #[test]
+    fn test_transform_request_passes_openai_payload_through() {
+        let payload = transform_request(
+            &request(json!({
+                "model": "default-model",
+                "messages": [{"role": "user", "content": "hi"}],
+                "temperature": 0.2,
+                "n": 2,
+                "stream": true,
+                "stream_options": {"include_usage": true},
+                "tools": [{"type": "function", "function": {"name": "ls", "parameters": {}}}]
+            })),
+            &backend(""),
+        );
+
+        assert_eq!(
+            payload["messages"],
+            json!([{"role": "user", "content": "hi"}])
+        );
+        assert_eq!(payload["temperature"], 0.2);
+        assert_eq!(payload["n"], 2);
+        assert_eq!(payload["tools"][0]["function"]["name"], "ls");
+        let object = payload.as_object().unwrap();
+        assert!(!object.contains_key("stream"));
+        assert!(!object.contains_key("stream_options"));
+        assert!(!object.contains_key("max_tokens"));
+    }
+
+    #[test]
+    fn test_validate_response_requires_choices() {
+        let backend = backend("");
+
+        AzureOpenAiProvider
+            .validate_response(
+                &json!({ "choices": [{ "message": { "content": "hi" } }] }),
+                &backend,
+            )
+            .unwrap();
+        // A Red Hat Lightspeed shaped body is not an Azure OpenAI answer
+        let result =
+            AzureOpenAiProvider.validate_response(&json!({ "data": { "text": "hi" } }), &backend);
+        assert!(matches!(result, Err(AppError::TransformError(_))));
+    }
+
+    #[test]
+    fn test_transform_request_applies_system_prompt() {
+        let payload = transform_request(
+            &request(json!({
+                "model": "default-model",
+                "messages": [
+                    {"role": "system", "content": "Be brief"},
+                    {"role": "user", "content": "hi"}
+                ]
+            })),
+            &backend("system_prompt = \"You help with RHEL\""),
+        );
+
+        assert_eq!(
+            payload["messages"],
+            json!([
+                {"role": "system", "content": "You help with RHEL\n\nBe brief"},
+                {"role": "user", "content": "hi"}
+            ])
+        );
+    }
+}
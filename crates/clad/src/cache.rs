@@ -0,0 +1,387 @@
+//! In-memory cache of chat completion responses
+//!
+//! Responses are keyed by a hash of the model, the parameters that change
+//! the output, the tools and the messages as sent. Entries expire after the
+//! configured TTL and the least recently used entry is evicted when the
+//! cache is full.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::config::CacheConfig;
+use crate::openai::{ChatCompletionRequest, ChatCompletionResponse};
+
+/// LRU cache of non-streaming responses
+#[derive(Debug)]
+pub struct ResponseCache {
+    /// Maximum number of entries
+    max_entries: usize,
+    /// How long an entry stays valid
+    ttl: Duration,
+    /// Cached responses by request key
+    entries: Mutex<HashMap<u64, CacheEntry>>,
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    response: ChatCompletionResponse,
+    inserted: Instant,
+    last_used: Instant,
+}
+
+impl ResponseCache {
+    /// Create an empty cache from the `[proxy.cache]` settings
+    pub fn new(config: &CacheConfig) -> Self {
+        Self {
+            max_entries: config.max_entries,
+            ttl: Duration::from_secs(config.ttl_seconds),
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Whether a request may be served from (and stored in) the cache
+    ///
+    /// Streaming requests and requests sampling with a temperature above
+    /// zero are never cached.
+    pub fn is_cacheable(request: &ChatCompletionRequest) -> bool {
+        !request.stream.unwrap_or(false) && request.temperature.is_none_or(|t| t <= 0.0)
+    }
+
+    /// Compute the cache key for a request
+    ///
+    /// Fields the proxy doesn't know, such as `logit_bias`, are part of the
+    /// key since the backend may act on them. Only streaming, the
+    /// temperature (unset or zero for cacheable requests) and `user` are
+    /// left out.
+    pub fn key(request: &ChatCompletionRequest) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        request.model.hash(&mut hasher);
+        request.max_tokens.hash(&mut hasher);
+        request.n.hash(&mut hasher);
+        request.top_p.map(f64::to_bits).hash(&mut hasher);
+        request.presence_penalty.map(f64::to_bits).hash(&mut hasher);
+        request
+            .frequency_penalty
+            .map(f64::to_bits)
+            .hash(&mut hasher);
+        request.stop.hash(&mut hasher);
+        request.logprobs.hash(&mut hasher);
+        request.top_logprobs.hash(&mut hasher);
+        request.seed.hash(&mut hasher);
+        canonical_json(&request.response_format).hash(&mut hasher);
+        canonical_json(&request.tools).hash(&mut hasher);
+        canonical_json(&request.tool_choice).hash(&mut hasher);
+        canonical_json(&request.extra).hash(&mut hasher);
+        for message in &request.messages {
+            message.role.hash(&mut hasher);
+            message.name.hash(&mut hasher);
+            // Every part counts, images included, not only the text
+            canonical_json(&message.content).hash(&mut hasher);
+            canonical_json(&message.tool_calls).hash(&mut hasher);
+            message.tool_call_id.hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+
+    /// Look up a cached response, dropping it if it has expired
+    pub fn get(&self, key: u64) -> Option<ChatCompletionResponse> {
+        let mut entries = self.lock();
+        let now = Instant::now();
+        match entries.get_mut(&key) {
+            Some(entry) if now.duration_since(entry.inserted) < self.ttl => {
+                entry.last_used = now;
+                Some(entry.response.clone())
+            }
+            Some(_) => {
+                entries.remove(&key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Store a response, evicting expired and least recently used entries
+    pub fn insert(&self, key: u64, response: ChatCompletionResponse) {
+        if self.max_entries == 0 {
+            return;
+        }
+
+        let mut entries = self.lock();
+        let now = Instant::now();
+        entries.retain(|_, entry| now.duration_since(entry.inserted) < self.ttl);
+
+        while entries.len() >= self.max_entries && !entries.contains_key(&key) {
+            let Some(oldest) = entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| *key)
+            else {
+                break;
+            };
+            entries.remove(&oldest);
+        }
+
+        entries.insert(
+            key,
+            CacheEntry {
+                response,
+                inserted: now,
+                last_used: now,
+            },
+        );
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<u64, CacheEntry>> {
+        self.entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// `value` as JSON with object keys in a fixed order, so that maps holding
+/// the same entries always hash the same
+fn canonical_json(value: &impl Serialize) -> String {
+    serde_json::to_value(value)
+        .map(|value| value.to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn request(value: serde_json::Value) -> ChatCompletionRequest {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn response(content: &str) -> ChatCompletionResponse {
+        serde_json::from_value(json!({
+            "id": "chatcmpl-test",
+            "object": "chat.completion",
+            "created": 0,
+            "model": "default-model",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": content},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 0, "completion_tokens": 1, "total_tokens": 1}
+        }))
+        .unwrap()
+    }
+
+    fn cache(max_entries: usize, ttl_seconds: u64) -> ResponseCache {
+        ResponseCache::new(&CacheConfig {
+            enabled: true,
+            max_entries,
+            ttl_seconds,
+        })
+    }
+
+    #[test]
+    fn test_key_covers_model_and_sampling() {
+        let a = request(json!({
+            "model": "default-model",
+            "messages": [{"role": "user", "content": "how do I restart httpd"}]
+        }));
+        let b = request(json!({
+            "model": "default-model",
+            "stream": false,
+            "user": "alice",
+            "messages": [{"role": "user", "content": "how do I restart httpd"}]
+        }));
+        let other_model = request(json!({
+            "model": "other-model",
+            "messages": [{"role": "user", "content": "how do I restart httpd"}]
+        }));
+
+        let seeded = request(json!({
+            "model": "default-model",
+            "seed": 42,
+            "messages": [{"role": "user", "content": "how do I restart httpd"}]
+        }));
+
+        let several = request(json!({
+            "model": "default-model",
+            "n": 2,
+            "messages": [{"role": "user", "content": "how do I restart httpd"}]
+        }));
+
+        assert_eq!(ResponseCache::key(&a), ResponseCache::key(&b));
+        assert_ne!(ResponseCache::key(&a), ResponseCache::key(&other_model));
+        assert_ne!(ResponseCache::key(&a), ResponseCache::key(&seeded));
+        assert_ne!(ResponseCache::key(&a), ResponseCache::key(&several));
+    }
+
+    #[test]
+    fn test_key_covers_tools_penalties_and_unknown_fields() {
+        let base = json!({
+            "model": "default-model",
+            "messages": [{"role": "user", "content": "what is the weather in Brno"}]
+        });
+        let with = |extra: serde_json::Value| {
+            let mut value = base.clone();
+            value
+                .as_object_mut()
+                .unwrap()
+                .extend(extra.as_object().unwrap().clone());
+            ResponseCache::key(&request(value))
+        };
+        let tool = json!([{
+            "type": "function",
+            "function": {"name": "weather", "parameters": {"type": "object"}}
+        }]);
+
+        let plain = with(json!({}));
+        let variants = [
+            with(json!({"tools": tool})),
+            with(json!({"tools": tool, "tool_choice": "none"})),
+            with(json!({"presence_penalty": 1.0})),
+            with(json!({"frequency_penalty": 1.0})),
+            with(json!({"logit_bias": {"1234": -100}})),
+        ];
+        for (i, key) in variants.iter().enumerate() {
+            assert_ne!(plain, *key, "variant {}", i);
+        }
+        assert_ne!(variants[0], variants[1]);
+
+        // Maps hash the same whatever order their keys came in
+        assert_eq!(
+            with(json!({"logit_bias": {"1": 1, "2": 2, "3": 3}})),
+            with(json!({"logit_bias": {"3": 3, "2": 2, "1": 1}}))
+        );
+    }
+
+    #[test]
+    fn test_key_covers_tool_calls_and_exact_text() {
+        let conversation = |call_id: &str, result: &str| {
+            ResponseCache::key(&request(json!({
+                "model": "default-model",
+                "messages": [
+                    {"role": "user", "content": "what is the weather in Brno"},
+                    {"role": "assistant", "content": "", "tool_calls": [{
+                        "id": call_id,
+                        "type": "function",
+                        "function": {"name": "weather", "arguments": "{\"city\":\"Brno\"}"}
+                    }]},
+                    {"role": "tool", "tool_call_id": call_id, "content": result}
+                ]
+            })))
+        };
+
+        assert_eq!(
+            conversation("call_1", "sunny"),
+            conversation("call_1", "sunny")
+        );
+        assert_ne!(
+            conversation("call_1", "sunny"),
+            conversation("call_2", "sunny")
+        );
+        assert_ne!(
+            conversation("call_1", "sunny"),
+            conversation("call_1", "rain")
+        );
+
+        // Indentation can change what a prompt means
+        let prompt = |text: &str| {
+            ResponseCache::key(&request(json!({
+                "model": "default-model",
+                "messages": [{"role": "user", "content": text}]
+            })))
+        };
+        assert_ne!(
+            prompt("fix this yaml:\na:\n  b: 1"),
+            prompt("fix this yaml:\na:\nb: 1")
+        );
+    }
+
+    #[test]
+    fn test_key_covers_image_parts() {
+        let with_image = |url: &str| {
+            ResponseCache::key(&request(json!({
+                "model": "default-model",
+                "messages": [{"role": "user", "content": [
+                    {"type": "text", "text": "what does this error say"},
+                    {"type": "image_url", "image_url": {"url": url}}
+                ]}]
+            })))
+        };
+        let text_only = ResponseCache::key(&request(json!({
+            "model": "default-model",
+            "messages": [{"role": "user", "content": [
+                {"type": "text", "text": "what does this error say"}
+            ]}]
+        })));
+
+        assert_eq!(
+            with_image("data:image/png;base64,AAAA"),
+            with_image("data:image/png;base64,AAAA")
+        );
+        assert_ne!(
+            with_image("data:image/png;base64,AAAA"),
+            with_image("data:image/png;base64,BBBB")
+        );
+        assert_ne!(with_image("data:image/png;base64,AAAA"), text_only);
+    }
+
+    #[test]
+    fn test_is_cacheable() {
+        let messages = json!([{"role": "user", "content": "hello"}]);
+        let cacheable = |extra: serde_json::Value| {
+            let mut value = json!({"model": "default-model", "messages": messages});
+            value
+                .as_object_mut()
+                .unwrap()
+                .extend(extra.as_object().unwrap().clone());
+            ResponseCache::is_cacheable(&request(value))
+        };
+
+        assert!(cacheable(json!({})));
+        assert!(cacheable(json!({"temperature": 0.0})));
+        assert!(!cacheable(json!({"temperature": 0.7})));
+        assert!(!cacheable(json!({"stream": true})));
+    }
+
+    #[test]
+    fn test_get_returns_inserted_response() {
+        let cache = cache(10, 60);
+        cache.insert(1, response("cached"));
+
+        assert_eq!(
+            cache.get(1).unwrap().choices[0].message.content.as_text(),
+            "cached"
+        );
+        assert!(cache.get(2).is_none());
+    }
+
+    #[test]
+    fn test_expired_entries_are_dropped() {
+        let cache = cache(10, 0);
+        cache.insert(1, response("cached"));
+
+        assert!(cache.get(1).is_none());
+    }
+
+    #[test]
+    fn test_least_recently_used_is_evicted() {
+        let cache = cache(2, 60);
+        cache.insert(1, response("one"));
+        std::thread::sleep(Duration::from_millis(2));
+        cache.insert(2, response("two"));
+        std::thread::sleep(Duration::from_millis(2));
+
+        // Touch 1 so that 2 becomes the least recently used entry
+        assert!(cache.get(1).is_some());
+        cache.insert(3, response("three"));
+
+        assert!(cache.get(1).is_some());
+        assert!(cache.get(2).is_none());
+        assert!(cache.get(3).is_some());
+    }
+}
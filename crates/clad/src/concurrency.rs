@@ -0,0 +1,117 @@
+//! Limit on simultaneous requests to the backend routes
+//!
+//! Rate limits bound how often requests arrive, not how many slow
+//! generations run at once. `[proxy] max_concurrent_requests` caps the
+//! requests in flight; a request holds its slot until the response body,
+//! streamed or not, has been sent. When every slot is taken, new requests
+//! wait up to `max_concurrent_wait_ms` and are then answered with 503.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::body::Body;
+use axum::extract::Request;
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::Router;
+use futures::StreamExt;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::provider::AppError;
+use crate::state::AppState;
+
+/// Limit the routes of `router` to `max_requests` requests in flight
+///
+/// Saturated requests wait up to `max_wait` for a slot. A limit of 0 leaves
+/// the routes unlimited.
+pub fn limit(
+    router: Router<AppState>,
+    max_requests: usize,
+    max_wait: Duration,
+) -> Router<AppState> {
+    if max_requests == 0 {
+        return router;
+    }
+
+    let slots = Arc::new(Semaphore::new(max_requests));
+    router.layer(middleware::from_fn(move |request: Request, next: Next| {
+        let slots = slots.clone();
+        async move {
+            let Some(permit) = acquire(slots, max_wait).await else {
+                return AppError::Overloaded.into_response();
+            };
+            let response = next.run(request).await;
+            hold_until_sent(response, permit)
+        }
+    }))
+}
+
+/// Take a slot, waiting at most `max_wait` for one to free up
+async fn acquire(slots: Arc<Semaphore>, max_wait: Duration) -> Option<OwnedSemaphorePermit> {
+    if let Ok(permit) = slots.clone().try_acquire_owned() {
+        return Some(permit);
+    }
+    if max_wait.is_zero() {
+        return None;
+    }
+    tokio::time::timeout(max_wait, slots.acquire_owned())
+        .await
+        .ok()?
+        .ok()
+}
+
+/// Keep `permit` until the response body has been sent or dropped
+fn hold_until_sent(response: Response, permit: OwnedSemaphorePermit) -> Response {
+    response.map(|body| {
+        Body::from_stream(body.into_data_stream().map(move |chunk| {
+            let _ = &permit;
+            chunk
+        }))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_acquire_fails_fast_when_saturated() {
+        let slots = Arc::new(Semaphore::new(1));
+        let held = acquire(slots.clone(), Duration::ZERO).await.unwrap();
+
+        assert!(acquire(slots.clone(), Duration::ZERO).await.is_none());
+        assert!(acquire(slots.clone(), Duration::from_millis(20))
+            .await
+            .is_none());
+
+        drop(held);
+        assert!(acquire(slots, Duration::ZERO).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_acquire_waits_for_a_released_slot() {
+        let slots = Arc::new(Semaphore::new(1));
+        let held = acquire(slots.clone(), Duration::ZERO).await.unwrap();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            drop(held);
+        });
+
+        assert!(acquire(slots, Duration::from_secs(5)).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_slot_is_held_until_body_is_consumed() {
+        let slots = Arc::new(Semaphore::new(1));
+        let permit = acquire(slots.clone(), Duration::ZERO).await.unwrap();
+
+        let response = hold_until_sent(Response::new(Body::from("hello")), permit);
+        assert_eq!(slots.available_permits(), 0);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body, "hello");
+        assert_eq!(slots.available_permits(), 1);
+    }
+}
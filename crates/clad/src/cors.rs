@@ -0,0 +1,108 @@
+//! CORS policy for browser-based clients
+
+use axum::http::{HeaderName, HeaderValue, Method};
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+use crate::config::CorsConfig;
+
+/// Build the CORS layer described by `[proxy.cors]`
+///
+/// Fails on method, header or origin values that are not valid HTTP tokens.
+pub fn cors_layer(config: &CorsConfig) -> Result<CorsLayer, String> {
+    let methods = config
+        .allowed_methods
+        .iter()
+        .map(|m| {
+            Method::from_bytes(m.to_uppercase().as_bytes())
+                .map_err(|_| format!("Invalid method '{}' in [proxy.cors] allowed_methods", m))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let headers = config
+        .allowed_headers
+        .iter()
+        .map(|h| {
+            HeaderName::from_bytes(h.as_bytes())
+                .map_err(|_| format!("Invalid header '{}' in [proxy.cors] allowed_headers", h))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let origin = match &config.allowed_origins {
+        Some(origins) => AllowOrigin::list(
+            origins
+                .iter()
+                .map(|o| {
+                    HeaderValue::from_str(o).map_err(|_| {
+                        format!("Invalid origin '{}' in [proxy.cors] allowed_origins", o)
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+        ),
+        // A wildcard origin can't be combined with credentials, so echo the
+        // request origin instead
+        None if config.allow_credentials => AllowOrigin::mirror_request(),
+        None => AllowOrigin::any(),
+    };
+
+    Ok(CorsLayer::new()
+        .allow_origin(origin)
+        .allow_methods(methods)
+        .allow_headers(headers)
+        .allow_credentials(config.allow_credentials))
+}
+
+/// Describe the effective CORS policy for the startup log
+pub fn describe(config: &CorsConfig) -> String {
+    let origins = match &config.allowed_origins {
+        Some(origins) => origins.join(", "),
+        None => "any".to_string(),
+    };
+    format!(
+        "origins: {}; methods: {}; headers: {}; credentials: {}",
+        origins,
+        config.allowed_methods.join(", "),
+        config.allowed_headers.join(", "),
+        config.allow_credentials
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cors_layer_defaults() {
+        assert!(cors_layer(&CorsConfig::default()).is_ok());
+        assert_eq!(
+            describe(&CorsConfig::default()),
+            "origins: any; methods: GET, POST, OPTIONS; headers: content-type, authorization; credentials: false"
+        );
+    }
+
+    #[test]
+    fn test_cors_layer_with_credentials() {
+        let config = CorsConfig {
+            allowed_headers: vec!["content-type".to_string(), "x-request-id".to_string()],
+            allow_credentials: true,
+            ..CorsConfig::default()
+        };
+
+        assert!(cors_layer(&config).is_ok());
+    }
+
+    #[test]
+    fn test_cors_layer_rejects_invalid_values() {
+        let bad_header = CorsConfig {
+            allowed_headers: vec!["not a header".to_string()],
+            ..CorsConfig::default()
+        };
+        assert!(cors_layer(&bad_header)
+            .unwrap_err()
+            .contains("not a header"));
+
+        let bad_method = CorsConfig {
+            allowed_methods: vec!["GE T".to_string()],
+            ..CorsConfig::default()
+        };
+        assert!(cors_layer(&bad_method).unwrap_err().contains("GE T"));
+    }
+}
@@ -0,0 +1,157 @@
+//! Provider answering every request with the user's own last message
+//!
+//! The echo provider calls no backend, so it needs neither an endpoint nor
+//! `[backend.auth]`. It lets the client wiring be checked end to end, for
+//! example goose talking to clad, without a real backend. The request still
+//! goes through redaction, model substitution and the hooks, and the answer
+//! is streamed like any other.
+
+use serde_json::{json, Value};
+
+use crate::config::BackendConfig;
+use crate::openai::ChatCompletionRequest;
+use crate::provider::{AppError, BackendReply};
+use crate::registry::Provider;
+
+/// Provider echoing the last user message
+#[derive(Debug)]
+pub struct EchoProvider;
+
+impl Provider for EchoProvider {
+    fn name(&self) -> &'static str {
+        "echo"
+    }
+
+    fn transform_request(
+        &self,
+        request: &ChatCompletionRequest,
+        _backend: &BackendConfig,
+    ) -> Value {
+        let text = request
+            .messages
+            .iter()
+            .rev()
+            .find(|message| message.role == "user")
+            .map(|message| message.content.as_text().into_owned())
+            .unwrap_or_default();
+        json!({ "text": text })
+    }
+
+    fn extract_reply(
+        &self,
+        backend_response: &Value,
+        _backend: &BackendConfig,
+    ) -> Result<BackendReply, AppError> {
+        let text = backend_response["text"]
+            .as_str()
+            .ok_or_else(|| AppError::TransformError("Echo payload has no text".to_string()))?;
+        Ok(BackendReply {
+            text: text.to_string(),
+            tool_calls: None,
+            logprobs: None,
+            system_fingerprint: None,
+        })
+    }
+
+    fn uses_backend(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::chat_completions_handler;
+    use crate::state::AppState;
+    use crate::test_support::serve;
+    use axum::routing::post;
+    use axum::Router;
+
+    /// Proxy using the echo provider, with no endpoint nor credentials
+    async fn serve_echo() -> String {
+        let config = toml::from_str("[backend]\nprovider = \"echo\"\n[backend.auth]").unwrap();
+        let state = AppState::builder().config(config).build();
+        serve(
+            Router::new()
+                .route("/v1/chat/completions", post(chat_completions_handler))
+                .with_state(state),
+        )
+        .await
+    }
+
+    fn request(stream: bool) -> Value {
+        json!({
+            "model": "default-model",
+            "stream": stream,
+            "messages": [
+                {"role": "system", "content": "You are helpful"},
+                {"role": "user", "content": "first question"},
+                {"role": "assistant", "content": "first answer"},
+                {"role": "user", "content": "how do I list open ports?"}
+            ]
+        })
+    }
+
+    #[tokio::test]
+    async fn test_echoes_last_user_message() {
+        let base = serve_echo().await;
+
+        let response: Value = reqwest::Client::new()
+            .post(format!("{}/v1/chat/completions", base))
+            .json(&request(false))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+
+        let message = &response["choices"][0]["message"];
+        assert_eq!(message["role"], "assistant");
+        assert_eq!(message["content"], "how do I list open ports?");
+    }
+
+    #[tokio::test]
+    async fn test_streams_the_echo() {
+        let base = serve_echo().await;
+
+        let body = reqwest::Client::new()
+            .post(format!("{}/v1/chat/completions", base))
+            .json(&request(true))
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+
+        let text: String = body
+            .lines()
+            .filter_map(|line| line.strip_prefix("data: "))
+            .filter_map(|data| serde_json::from_str::<Value>(data).ok())
+            .filter_map(|chunk| {
+                chunk["choices"][0]["delta"]["content"]
+                    .as_str()
+                    .map(String::from)
+            })
+            .collect();
+        assert_eq!(text, "how do I list open ports?");
+    }
+
+    #[test]
+    fn test_echo_without_user_message_is_empty() {
+        let request: ChatCompletionRequest = serde_json::from_value(json!({
+            "model": "default-model",
+            "messages": [{"role": "system", "content": "You are helpful"}]
+        }))
+        .unwrap();
+        let backend: BackendConfig = toml::from_str("[auth]").unwrap();
+
+        let payload = EchoProvider.transform_request(&request, &backend);
+
+        assert_eq!(
+            EchoProvider.extract_reply(&payload, &backend).unwrap().text,
+            ""
+        );
+    }
+}
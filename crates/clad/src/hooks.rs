@@ -0,0 +1,227 @@
+//! Hooks that adjust chat completions around the provider
+//!
+//! Hooks are configured as an ordered `[[backend.hooks]]` list. Each one can
+//! change the request (and add headers) before the provider transforms it,
+//! and the backend response before the provider extracts the reply, so small
+//! adjustments don't need a provider of their own.
+
+use std::fmt::Debug;
+
+use axum::http::{HeaderMap, HeaderName, HeaderValue};
+use serde_json::Value;
+
+use crate::config::HookConfig;
+use crate::openai::ChatCompletionRequest;
+
+/// A transformation applied around the provider
+///
+/// Both methods do nothing by default, so hooks only implement the side
+/// they care about.
+pub trait Hook: Send + Sync + Debug {
+    /// Adjust the request, and the headers sent with it, before the provider
+    /// transforms it
+    fn pre(&self, _request: &mut ChatCompletionRequest, _headers: &mut HeaderMap) {}
+
+    /// Adjust the backend response before the provider extracts the reply
+    fn post(&self, _response: &mut Value) {}
+}
+
+/// Adds a header to every backend request
+#[derive(Debug)]
+pub struct HeaderHook {
+    name: HeaderName,
+    value: HeaderValue,
+}
+
+impl Hook for HeaderHook {
+    fn pre(&self, _request: &mut ChatCompletionRequest, headers: &mut HeaderMap) {
+        headers.insert(self.name.clone(), self.value.clone());
+    }
+}
+
+/// Sends one model name to the backend as another
+#[derive(Debug)]
+pub struct RenameModelHook {
+    from: String,
+    to: String,
+}
+
+impl Hook for RenameModelHook {
+    fn pre(&self, request: &mut ChatCompletionRequest, _headers: &mut HeaderMap) {
+        if request.model == self.from {
+            request.model = self.to.clone();
+        }
+    }
+}
+
+/// Create the hooks described by `[[backend.hooks]]`, in order
+pub fn build(configs: &[HookConfig]) -> Vec<Box<dyn Hook>> {
+    configs
+        .iter()
+        .map(|config| -> Box<dyn Hook> {
+            match config {
+                HookConfig::Header { name, value } => Box::new(HeaderHook {
+                    name: name.clone(),
+                    value: value.clone(),
+                }),
+                HookConfig::RenameModel { from, to } => Box::new(RenameModelHook {
+                    from: from.clone(),
+                    to: to.clone(),
+                }),
+            }
+        })
+        .collect()
+}
+
+/// Run the `pre` side of every hook, returning the headers they added
+pub fn run_pre(hooks: &[Box<dyn Hook>], request: &mut ChatCompletionRequest) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    for hook in hooks {
+        hook.pre(request, &mut headers);
+    }
+    headers
+}
+
+/// Run the `post` side of every hook
+pub fn run_post(hooks: &[Box<dyn Hook>], response: &mut Value) {
+    for hook in hooks {
+        hook.post(response);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use serde_json::json;
+
+    fn hooks(toml_hooks: &str) -> Vec<Box<dyn Hook>> {
+        let config: Config = toml::from_str(&format!(
+            r#"
+            [backend]
+            endpoint = "http://localhost:9000"
+
+            [backend.auth]
+            token = "secret"
+
+            {}
+        "#,
+            toml_hooks
+        ))
+        .unwrap();
+        build(&config.backend.hooks)
+    }
+
+    fn request(model: &str) -> ChatCompletionRequest {
+        serde_json::from_value(json!({
+            "model": model,
+            "messages": [{"role": "user", "content": "hello"}]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_hooks_run_in_order() {
+        let hooks = hooks(
+            r#"
+            [[backend.hooks]]
+            type = "rename_model"
+            from = "granite"
+            to = "granite-8b"
+
+            [[backend.hooks]]
+            type = "rename_model"
+            from = "granite-8b"
+            to = "granite-3-8b-instruct"
+
+            [[backend.hooks]]
+            type = "header"
+            name = "x-tenant-id"
+            value = "acme"
+        "#,
+        );
+        let mut request = request("granite");
+
+        let headers = run_pre(&hooks, &mut request);
+
+        assert_eq!(request.model, "granite-3-8b-instruct");
+        assert_eq!(headers["x-tenant-id"], "acme");
+    }
+
+    #[test]
+    fn test_rename_model_ignores_other_models() {
+        let hooks = hooks(
+            r#"
+            [[backend.hooks]]
+            type = "rename_model"
+            from = "granite"
+            to = "granite-8b"
+        "#,
+        );
+        let mut request = request("mistral");
+
+        let headers = run_pre(&hooks, &mut request);
+
+        assert_eq!(request.model, "mistral");
+        assert!(headers.is_empty());
+    }
+
+    /// Hook implementing both sides, as a custom hook would
+    #[derive(Debug)]
+    struct SignatureHook;
+
+    impl Hook for SignatureHook {
+        fn pre(&self, request: &mut ChatCompletionRequest, _headers: &mut HeaderMap) {
+            if let Some(text) = request
+                .messages
+                .last_mut()
+                .and_then(|message| message.content.texts_mut().pop())
+            {
+                text.push_str("\n-- ops team");
+            }
+        }
+
+        fn post(&self, response: &mut Value) {
+            response["data"]["signed"] = Value::Bool(true);
+        }
+    }
+
+    #[test]
+    fn test_custom_hook_sees_request_and_response() {
+        let hooks: Vec<Box<dyn Hook>> = vec![Box::new(SignatureHook)];
+        let mut request = request("granite");
+        let mut response = json!({ "data": { "text": "hi" } });
+
+        run_pre(&hooks, &mut request);
+        run_post(&hooks, &mut response);
+
+        assert_eq!(request.messages[0].content.as_text(), "hello\n-- ops team");
+        assert_eq!(
+            response,
+            json!({ "data": { "text": "hi", "signed": true } })
+        );
+    }
+
+    #[test]
+    fn test_invalid_hooks_are_rejected() {
+        for (hook, expected) in [
+            (
+                "type = \"header\"\nname = \"bad header\"\nvalue = \"x\"",
+                "invalid header name",
+            ),
+            ("type = \"lowercase\"", "unknown variant"),
+            (
+                "type = \"rename_model\"\nfrom = \"a\"",
+                "missing field `to`",
+            ),
+        ] {
+            let err = toml::from_str::<Config>(&format!(
+                "[backend]\nendpoint = \"http://localhost:9000\"\n[backend.auth]\ntoken = \"secret\"\n[[backend.hooks]]\n{}",
+                hook
+            ))
+            .unwrap_err();
+
+            assert!(err.to_string().contains(expected), "{}", err);
+        }
+    }
+}
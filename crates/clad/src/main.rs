@@ -3,12 +3,14 @@
 //! This service provides an Ollama-compatible chat completions API that can be used
 //! as a provider for Goose (https://github.com/block/goose) and other AI clients.
 //!
-//! CLAD always listens on 127.0.0.1:8080 for incoming requests.
+//! CLAD listens on 127.0.0.1:8080 for incoming requests by default, or on the
+//! TCP address or Unix socket set by `[proxy] listen`.
 //!
 //! SETUP:
 //! 1. Copy config.toml.example to config.toml and configure:
-//!    - backend.endpoint: Your inference backend endpoint
-//!    - backend.auth: Certificate and key files for authentication
+//!    - backend.endpoint: Your inference backend endpoint (or backend.endpoints
+//!      to fail over between several backends)
+//!    - backend.auth: Certificate and key files, or a bearer token, for authentication
 //!    - backend.proxies: (Optional) HTTP/HTTPS proxy for outgoing backend requests
 //!
 //! 2. Build and run:
@@ -24,75 +26,175 @@
 //!        base_url: http://127.0.0.1:8080
 //!        model: default-model
 //!
+//!    The configuration may also be written as config.yaml or config.json.
+//!
+//! RELOADING:
+//!    Send SIGHUP to re-read the configuration without restarting the service:
+//!    $ kill -HUP $(pidof clad)
+//!
 //! API COMPATIBILITY:
 //! - Supports OpenAI-compatible chat completions API
 //! - Compatible with Ollama's extended features (tool calling)
 //! - Handles both streaming and non-streaming requests
 //!
+mod admin;
+mod audit;
+mod azure_openai;
+mod cache;
+mod concurrency;
 mod config;
+mod cors;
+mod echo;
+mod hooks;
 mod openai;
 mod provider;
+mod rate_limit;
+mod readiness;
+mod redaction;
+mod registry;
+mod startup_check;
 mod state;
+mod telemetry;
+#[cfg(test)]
+mod test_support;
+mod tls;
+mod unix_socket;
 
 use axum::{
+    extract::DefaultBodyLimit,
     routing::{get, post},
     Router,
 };
-use std::{net::SocketAddr, path::Path, sync::Arc};
-use tracing::info;
+use clap::Parser;
+use std::{
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+use tokio::signal::unix::{signal, SignalKind};
+use tower_http::compression::CompressionLayer;
+use tower_http::limit::RequestBodyLimitLayer;
+use tracing::{error, info, warn};
 
 use crate::{
-    config::Config,
+    config::{
+        AuthConfig, BackendConfig, Config, ConfigFormat, ListenAddress, ProxyConfig,
+        RateLimitConfig, RateLimitKey,
+    },
     provider::{
-        chat_completions_handler, create_authenticated_client, health_check_handler, models_handler,
+        chat_completions_handler, create_authenticated_client, embeddings_handler,
+        health_check_handler, models_handler, ready_handler, unknown_route_handler,
     },
+    registry::ProviderRegistry,
     state::AppState,
 };
 
+/// Command line arguments for the proxy server
+#[derive(Parser, Debug)]
+#[command(name = "clad", version, about)]
+struct Args {
+    /// Path to the configuration file
+    /// [default: $XDG_CONFIG_DIRS/command-line-assistant/config.toml]
+    #[arg(long, env = "CLAD_CONFIG", value_name = "PATH")]
+    config: Option<PathBuf>,
+}
+
+/// Resolve the configuration file path
+///
+/// `--config` wins over `CLAD_CONFIG`, which wins over the XDG default.
+fn resolve_config_path(config: Option<PathBuf>) -> PathBuf {
+    config.unwrap_or_else(|| {
+        let config_path =
+            std::env::var("XDG_CONFIG_DIRS").unwrap_or_else(|_| "/etc/xdg".to_string());
+        find_config_file(&Path::new(&config_path).join("command-line-assistant"))
+    })
+}
+
+/// The first configuration file present in `dir`
+///
+/// config.toml is preferred over the YAML and JSON variants, and is also
+/// returned when none exists, so errors name the expected file.
+fn find_config_file(dir: &Path) -> PathBuf {
+    ConfigFormat::FILE_NAMES
+        .iter()
+        .map(|name| dir.join(name))
+        .find(|path| path.is_file())
+        .unwrap_or_else(|| dir.join(ConfigFormat::FILE_NAMES[0]))
+}
+
+/// Directory of the user's configuration layered over the system one
+///
+/// This is `$XDG_CONFIG_HOME/command-line-assistant`, or
+/// `~/.config/command-line-assistant` when that variable is unset or empty.
+fn user_config_dir() -> Option<PathBuf> {
+    let non_empty = |name| std::env::var_os(name).filter(|value| !value.is_empty());
+    let config_home = non_empty("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| non_empty("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_home.join("command-line-assistant"))
+}
+
+/// Configuration files to load, in order
+///
+/// `base` comes first, followed by the configuration file in `user_dir`
+/// when there is one and it is not `base` itself.
+fn config_layers(base: PathBuf, user_dir: Option<&Path>) -> Vec<PathBuf> {
+    let user = user_dir
+        .map(find_config_file)
+        .filter(|path| path.is_file())
+        .filter(|path| match (path.canonicalize(), base.canonicalize()) {
+            (Ok(user), Ok(base)) => user != base,
+            _ => *path != base,
+        });
+    std::iter::once(base).chain(user).collect()
+}
+
+/// File names of `paths`, for logs
+fn display_paths(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 /// Main entry point for the proxy server
 #[tokio::main]
 async fn main() {
+    let args = Args::parse();
+
     // Load configuration first (before logging is initialized)
-    let config_path = std::env::var("XDG_CONFIG_DIRS").unwrap_or_else(|_| "/etc/xdg".to_string());
-    let config_file = Path::new(&config_path)
-        .join("command-line-assistant")
-        .join("config.toml");
+    let config_file = resolve_config_path(args.config);
+    let config_files = config_layers(config_file.clone(), user_config_dir().as_deref());
 
     // Load config to get the log level
-    let config = match std::fs::read_to_string(&config_file) {
-        Ok(contents) => {
-            match toml::from_str::<Config>(&contents) {
-                Ok(cfg) => cfg,
-                Err(e) => {
-                    eprintln!(
-                        "Failed to parse config from {}: {}",
-                        config_file.display(),
-                        e
-                    );
-                    eprintln!("Please check your config.toml file. See config.toml.example for reference.");
-                    std::process::exit(1);
-                }
-            }
-        }
+    if !config_file.is_file() {
+        eprintln!("Failed to read config from {}", config_file.display());
+        eprintln!("Please create a config.toml file. See config.toml.example for reference.");
+        std::process::exit(1);
+    }
+    let config = match Config::from_files(&config_files) {
+        Ok(cfg) => cfg,
         Err(e) => {
-            eprintln!(
-                "Failed to read config from {}: {}",
-                config_file.display(),
-                e
-            );
-            eprintln!("Please create a config.toml file. See config.toml.example for reference.");
+            eprintln!("{}", e);
+            eprintln!("Please check your config file. See config.toml.example for reference.");
             std::process::exit(1);
         }
     };
 
-    // Initialize logging with the configured log level
+    // Initialize logging with the configured log level. RUST_LOG, when set,
+    // takes precedence and is not overridden by configuration reloads.
     let filter_string = config.get_tracing_filter();
-    tracing_subscriber::fmt()
+    let log_level_from_env = std::env::var("RUST_LOG").is_ok();
+    let subscriber = tracing_subscriber::fmt()
         .with_env_filter(
             tracing_subscriber::EnvFilter::try_from_default_env()
                 .unwrap_or_else(|_| filter_string.into()),
         )
-        .init();
+        .with_filter_reloading();
+    let log_filter_handle = subscriber.reload_handle();
+    subscriber.init();
 
     // Now emit deprecation warnings if needed
     if config.database.is_some() {
@@ -104,17 +206,34 @@ async fn main() {
     }
 
     if config.logging.audit.is_some() {
-        tracing::warn!("The [logging.audit] configuration section is deprecated and will be removed in a future version");
+        tracing::warn!("The [logging.audit] configuration section is deprecated and ignored, use [audit] file instead");
     }
 
+    info!("Loaded configuration from {}", display_paths(&config_files));
     info!("Using log level from config: {}", config.logging.level);
 
-    // CLAD always listens on 127.0.0.1:8080
-    const LISTEN_HOST: &str = "127.0.0.1";
-    const LISTEN_PORT: u16 = 8080;
+    info!("Starting CLAD service on {}", config.proxy.listen);
 
-    info!("Starting CLAD service on {}:{}", LISTEN_HOST, LISTEN_PORT);
-    info!("Backend endpoint: {}", config.backend.endpoint);
+    // Select the backend provider
+    let registry = Arc::new(ProviderRegistry::with_builtin());
+    let provider = registry
+        .create(&config.backend.provider)
+        .and_then(|provider| provider.validate(&config.backend).map(|()| provider))
+        .unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        });
+    info!("Using provider: {}", provider.name());
+
+    let endpoints = config.backend.endpoints();
+    if !provider.uses_backend() {
+        info!("The {} provider answers without a backend", provider.name());
+    } else if endpoints.is_empty() {
+        eprintln!("No backend endpoint configured. Set backend.endpoint or backend.endpoints.");
+        std::process::exit(1);
+    } else {
+        info!("Backend endpoints: {}", endpoints.join(", "));
+    }
 
     if let Some(proxies) = &config.backend.proxies {
         if !proxies.is_empty() {
@@ -128,28 +247,166 @@ async fn main() {
         }
     }
 
-    // Create HTTP client with certificate-based authentication
-    let client = create_authenticated_client(&config).unwrap_or_else(|e| {
+    let backend = &config.backend;
+    info!(
+        "Backend connection pool: max idle per host {}, idle timeout {}, HTTP/2 prior knowledge {}",
+        backend
+            .pool_max_idle_per_host
+            .map_or("unlimited".to_string(), |max| max.to_string()),
+        backend
+            .pool_idle_timeout()
+            .map_or("none".to_string(), |timeout| format!("{:?}", timeout)),
+        if backend.http2_prior_knowledge {
+            "on"
+        } else {
+            "off"
+        }
+    );
+
+    // Create HTTP client with certificate or token authentication
+    let client = create_authenticated_client(&config, provider.as_ref()).unwrap_or_else(|e| {
         eprintln!("Failed to create HTTP client: {}", e);
         std::process::exit(1);
     });
 
     // Create shared state
-    let config_arc = Arc::new(config);
-    let state = AppState {
-        config: config_arc.clone(),
-        client,
-    };
+    let uses_backend = provider.uses_backend();
+    let startup_check = (config.backend.startup_check && uses_backend).then(|| {
+        config
+            .backend
+            .endpoints()
+            .into_iter()
+            .map(String::from)
+            .collect::<Vec<_>>()
+    });
+    let metrics_enabled = config.proxy.metrics_enabled;
+    let rate_limit = config.proxy.rate_limit.clone();
+    let cors_config = config.proxy.cors.clone();
+    let compress_responses = config.proxy.compress_responses;
+    let listen = config.proxy.listen.clone();
+    let tls_config = config.proxy.tls.clone();
+    let max_body_bytes = config.proxy.max_body_bytes;
+    let concurrency = ConcurrencyLimit::from(&config.proxy);
+    let state = AppState::new(config, client, provider, registry.clone());
+
+    // Check the backend credentials in the background, without delaying startup
+    if let Some(endpoints) = startup_check {
+        let snapshot = state.snapshot();
+        tokio::spawn(startup_check::run(
+            snapshot.client.clone(),
+            endpoints,
+            snapshot.readiness.clone(),
+        ));
+    }
+
+    // Reload the configuration on SIGHUP without dropping connections
+    let mut sighup = signal(SignalKind::hangup()).unwrap_or_else(|e| {
+        eprintln!("Failed to install SIGHUP handler: {}", e);
+        std::process::exit(1);
+    });
+    let reload_state = state.clone();
+    tokio::spawn(async move {
+        while sighup.recv().await.is_some() {
+            info!(
+                "Received SIGHUP, reloading configuration from {}",
+                display_paths(&config_files)
+            );
+            match reload_config(&reload_state, &registry, &config_files) {
+                Ok(config) => {
+                    if log_level_from_env {
+                        continue;
+                    }
+                    let filter = tracing_subscriber::EnvFilter::new(config.get_tracing_filter());
+                    if let Err(e) = log_filter_handle.reload(filter) {
+                        warn!("Failed to apply log level {}: {}", config.logging.level, e);
+                    }
+                }
+                Err(e) => {
+                    error!(
+                        "Failed to reload configuration, keeping the current one: {}",
+                        e
+                    );
+                }
+            }
+        }
+    });
+
+    if let ListenAddress::Unix(_) = listen {
+        // Unix sockets have neither TLS nor peer IP addresses
+        if tls_config.is_some() {
+            eprintln!("[proxy.tls] can't be used with a unix: listen address");
+            std::process::exit(1);
+        }
+        if rate_limit.key_by == RateLimitKey::Ip {
+            eprintln!(
+                "[proxy.rate_limit] key_by = \"ip\" can't be used with a unix: listen address"
+            );
+            std::process::exit(1);
+        }
+    }
+
+    if rate_limit.key_by == RateLimitKey::Header {
+        warn!("[proxy.rate_limit] key_by = \"header\" trusts the X-Forwarded-For and Authorization headers, only use it behind a reverse proxy that sets them");
+    }
 
     // Build application with all middleware
-    let app = Router::new()
-        .route("/health", get(health_check_handler))
-        .route("/v1/chat/completions", post(chat_completions_handler))
-        .route("/v1/models", get(models_handler))
-        .with_state(state);
+    let mut app = build_router(state, &rate_limit, concurrency, max_body_bytes);
+
+    // Answer cross-origin requests if a CORS policy is configured
+    if let Some(cors_config) = &cors_config {
+        let layer = cors::cors_layer(cors_config).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        });
+        app = app.layer(layer);
+        info!("CORS policy: {}", cors::describe(cors_config));
+    } else {
+        info!("CORS disabled");
+    }
+
+    // Expose Prometheus metrics if enabled
+    if metrics_enabled {
+        let handle = telemetry::install_recorder().unwrap_or_else(|e| {
+            eprintln!("Failed to install metrics recorder: {}", e);
+            std::process::exit(1);
+        });
+        app = app.route("/metrics", get(move || async move { handle.render() }));
+        info!("Prometheus metrics available at /metrics");
+    }
+
+    // Compress responses for clients that ask for it; the default predicate
+    // leaves event streams alone
+    if compress_responses {
+        app = app.layer(CompressionLayer::new());
+        info!("Response compression enabled");
+    }
+
+    // Load the listener certificate before binding, so a bad one fails fast
+    let tls = match &tls_config {
+        Some(tls_config) => Some(tls::load(tls_config).await.unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        })),
+        None => None,
+    };
 
     // Bind and serve
-    let addr = format!("{}:{}", LISTEN_HOST, LISTEN_PORT);
+    let addr = match listen {
+        ListenAddress::Tcp(addr) => addr,
+        ListenAddress::Unix(path) => {
+            let listener = unix_socket::bind(&path).unwrap_or_else(|e| {
+                eprintln!("Failed to bind to {}: {}", path.display(), e);
+                std::process::exit(1);
+            });
+            info!("CLAD service listening on unix:{}", path.display());
+
+            if let Err(e) = unix_socket::serve(listener, app).await {
+                eprintln!("Server error: {}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+    };
     let socket_addr: SocketAddr = addr.parse().unwrap_or_else(|e| {
         eprintln!("Invalid address '{}': {}", addr, e);
         std::process::exit(1);
@@ -163,10 +420,662 @@ async fn main() {
             std::process::exit(1);
         });
 
-    info!("CLAD service listening on {}", socket_addr);
+    let scheme = if tls.is_some() { "https" } else { "http" };
+    info!("CLAD service listening on {}://{}", scheme, socket_addr);
 
-    if let Err(e) = axum::serve(listener, app).await {
+    if let Err(e) = tls::serve(listener, app, tls).await {
         eprintln!("Server error: {}", e);
         std::process::exit(1);
     }
 }
+
+/// Limit on requests in flight, from `[proxy]`
+#[derive(Clone, Copy, Debug)]
+struct ConcurrencyLimit {
+    max_requests: usize,
+    max_wait: Duration,
+}
+
+impl From<&ProxyConfig> for ConcurrencyLimit {
+    fn from(proxy: &ProxyConfig) -> Self {
+        Self {
+            max_requests: proxy.max_concurrent_requests,
+            max_wait: Duration::from_millis(proxy.max_concurrent_wait_ms),
+        }
+    }
+}
+
+/// Build the API router
+///
+/// Chat completions and embeddings share one rate limit and one limit on
+/// requests in flight, model listing has its own rate limit, and health
+/// checks, the readiness probe and the admin endpoint are never limited.
+/// Request bodies larger than `max_body_bytes` are rejected, and unknown
+/// routes get an OpenAI-style 404.
+fn build_router(
+    state: AppState,
+    rate_limit: &RateLimitConfig,
+    concurrency: ConcurrencyLimit,
+    max_body_bytes: usize,
+) -> Router {
+    let completions = concurrency::limit(
+        Router::new()
+            .route("/v1/chat/completions", post(chat_completions_handler))
+            .route("/v1/embeddings", post(embeddings_handler)),
+        concurrency.max_requests,
+        concurrency.max_wait,
+    );
+    let models = Router::new().route("/v1/models", get(models_handler));
+
+    Router::new()
+        .route("/health", get(health_check_handler))
+        .route("/ready", get(ready_handler))
+        .route("/admin/recent", get(admin::recent_handler))
+        .route("/v1/debug/config", get(admin::config_handler))
+        .merge(rate_limit::limit(
+            completions,
+            rate_limit.completions_per_second,
+            rate_limit,
+            rate_limit.per_user,
+        ))
+        .merge(rate_limit::limit(
+            models,
+            rate_limit.models_per_second,
+            rate_limit,
+            false,
+        ))
+        .fallback(unknown_route_handler)
+        // Oversized bodies are rejected with 413 before they are buffered
+        .layer(DefaultBodyLimit::disable())
+        .layer(RequestBodyLimitLayer::new(max_body_bytes))
+        .with_state(state)
+}
+
+/// Reload the configuration files and swap them into the shared state
+///
+/// The HTTP client is only rebuilt when the settings it was created from
+/// (authentication, timeouts or proxies) have changed. On any error the
+/// current configuration is kept.
+fn reload_config(
+    state: &AppState,
+    registry: &ProviderRegistry,
+    config_files: &[PathBuf],
+) -> Result<Arc<Config>, Box<dyn std::error::Error>> {
+    let new_config = Config::from_files(config_files)?;
+    let current = state.snapshot();
+    let provider = registry.create(&new_config.backend.provider)?;
+    provider.validate(&new_config.backend)?;
+    if provider.uses_backend() && new_config.backend.endpoints().is_empty() {
+        return Err("No backend endpoint configured".into());
+    }
+
+    if new_config.proxy.metrics_enabled != current.config.proxy.metrics_enabled {
+        warn!("Changing proxy.metrics_enabled requires a restart to take effect");
+    }
+    if new_config.proxy.rate_limit != current.config.proxy.rate_limit {
+        warn!("Changing [proxy.rate_limit] requires a restart to take effect");
+    }
+    if new_config.proxy.max_body_bytes != current.config.proxy.max_body_bytes {
+        warn!("Changing proxy.max_body_bytes requires a restart to take effect");
+    }
+    if new_config.proxy.max_concurrent_requests != current.config.proxy.max_concurrent_requests
+        || new_config.proxy.max_concurrent_wait_ms != current.config.proxy.max_concurrent_wait_ms
+    {
+        warn!("Changing proxy.max_concurrent_requests requires a restart to take effect");
+    }
+    if new_config.proxy.listen != current.config.proxy.listen {
+        warn!("Changing proxy.listen requires a restart to take effect");
+    }
+    if new_config.proxy.tls != current.config.proxy.tls {
+        warn!("Changing [proxy.tls] requires a restart to take effect");
+    }
+    if new_config.proxy.cors != current.config.proxy.cors {
+        warn!("Changing [proxy.cors] requires a restart to take effect");
+    }
+
+    let client = if client_settings_changed(&current.config.backend, &new_config.backend) {
+        info!("Backend client settings changed, rebuilding HTTP client");
+        create_authenticated_client(&new_config, provider.as_ref())?
+    } else {
+        current.client.clone()
+    };
+
+    state.replace(new_config, client, provider);
+    info!("Configuration reloaded");
+
+    Ok(state.snapshot().config.clone())
+}
+
+/// Check whether the settings used to build the HTTP client have changed
+///
+/// Credentials read from files count as changed on every reload, since a
+/// renewed certificate or token usually keeps its path.
+fn client_settings_changed(old: &BackendConfig, new: &BackendConfig) -> bool {
+    // The provider decides how the auth token is sent
+    old.provider != new.provider
+        || old.auth != new.auth
+        || reads_credential_files(&new.auth)
+        || old.timeout != new.timeout
+        || old.connect_timeout != new.connect_timeout
+        || old.read_timeout != new.read_timeout
+        || old.streaming_timeout != new.streaming_timeout
+        || old.proxies != new.proxies
+        || old.pool_max_idle_per_host != new.pool_max_idle_per_host
+        || old.pool_idle_timeout_secs != new.pool_idle_timeout_secs
+        || old.http2_prior_knowledge != new.http2_prior_knowledge
+        || old.accept_compression != new.accept_compression
+}
+
+/// Whether `auth` reads the certificate, key, password or token from a file
+fn reads_credential_files(auth: &AuthConfig) -> bool {
+    [
+        &auth.cert_file,
+        &auth.key_file,
+        &auth.pkcs12_file,
+        &auth.pkcs12_password_file,
+        &auth.token_file,
+    ]
+    .iter()
+    .any(|file| file.is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_flag_takes_precedence() {
+        let args = Args::try_parse_from(["clad", "--config", "./my-config.toml"]).unwrap();
+
+        assert_eq!(
+            resolve_config_path(args.config),
+            PathBuf::from("./my-config.toml")
+        );
+    }
+
+    #[test]
+    fn test_config_path_defaults_to_xdg() {
+        let path = resolve_config_path(None);
+
+        assert!(path.ends_with("command-line-assistant/config.toml"));
+    }
+
+    #[test]
+    fn test_find_config_file_order() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let dir = temp_dir.path();
+
+        assert_eq!(find_config_file(dir), dir.join("config.toml"));
+        for name in ["config.json", "config.yaml", "config.toml"] {
+            std::fs::write(dir.join(name), "").unwrap();
+            assert_eq!(find_config_file(dir), dir.join(name));
+        }
+    }
+
+    #[test]
+    fn test_config_layers_add_user_file() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        let user_dir = dir.join("user");
+        std::fs::create_dir_all(&user_dir).unwrap();
+        let base = dir.join("config.toml");
+        std::fs::write(&base, "").unwrap();
+
+        let without_user = config_layers(base.clone(), Some(&user_dir));
+        std::fs::write(user_dir.join("config.yaml"), "").unwrap();
+        let with_user = config_layers(base.clone(), Some(&user_dir));
+        let user_is_base = config_layers(base.clone(), Some(dir));
+
+        assert_eq!(without_user, [base.clone()]);
+        assert_eq!(with_user, [base.clone(), user_dir.join("config.yaml")]);
+        assert_eq!(user_is_base, [base.clone()]);
+        assert_eq!(config_layers(base.clone(), None), [base]);
+    }
+
+    fn write_config(path: &Path, endpoint: &str, token: &str, level: &str) {
+        let contents = format!(
+            r#"
+            [backend]
+            endpoint = "{}"
+
+            [backend.auth]
+            token = "{}"
+
+            [logging]
+            level = "{}"
+        "#,
+            endpoint, token, level
+        );
+        std::fs::write(path, contents).unwrap();
+    }
+
+    /// Path of a config file in a new temporary directory, removed with it
+    fn temp_config_path() -> (tempfile::TempDir, std::path::PathBuf) {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("config.toml");
+        (dir, path)
+    }
+
+    #[test]
+    fn test_reload_config_swaps_config() {
+        let (_dir, path) = temp_config_path();
+        write_config(&path, "http://old:9000", "secret", "INFO");
+        let config = Config::from_files(&[&path]).unwrap();
+        let state = AppState::builder().config(config).build();
+
+        write_config(&path, "http://new:9000", "secret", "DEBUG");
+        let result = reload_config(&state, &ProviderRegistry::with_builtin(), &[path.clone()]);
+
+        let reloaded = result.unwrap();
+        assert_eq!(reloaded.backend.endpoint, "http://new:9000");
+        assert_eq!(reloaded.get_tracing_filter(), "clad=debug");
+        assert_eq!(state.snapshot().config.backend.endpoint, "http://new:9000");
+    }
+
+    #[test]
+    fn test_reload_config_keeps_current_on_error() {
+        let (_dir, path) = temp_config_path();
+        write_config(&path, "http://old:9000", "secret", "INFO");
+        let config = Config::from_files(&[&path]).unwrap();
+        let state = AppState::builder().config(config).build();
+
+        std::fs::write(&path, "not valid toml [").unwrap();
+        let result = reload_config(&state, &ProviderRegistry::with_builtin(), &[path.clone()]);
+
+        assert!(result.is_err());
+        assert_eq!(state.snapshot().config.backend.endpoint, "http://old:9000");
+    }
+
+    #[test]
+    fn test_client_settings_changed() {
+        let (_dir, path) = temp_config_path();
+        write_config(&path, "http://old:9000", "secret", "INFO");
+        let old = Config::from_files(&[&path]).unwrap();
+        write_config(&path, "http://new:9000", "secret", "DEBUG");
+        let endpoint_changed = Config::from_files(&[&path]).unwrap();
+        write_config(&path, "http://old:9000", "rotated", "INFO");
+        let token_changed = Config::from_files(&[&path]).unwrap();
+
+        assert!(!client_settings_changed(
+            &old.backend,
+            &endpoint_changed.backend
+        ));
+        assert!(client_settings_changed(
+            &old.backend,
+            &token_changed.backend
+        ));
+
+        // Renewed certificates keep their paths
+        let mut with_cert = old.backend.clone();
+        with_cert.auth = AuthConfig {
+            cert_file: Some("/etc/pki/consumer/cert.pem".to_string()),
+            key_file: Some("/etc/pki/consumer/key.pem".to_string()),
+            pkcs12_file: None,
+            pkcs12_password: None,
+            pkcs12_password_file: None,
+            token: None,
+            token_file: None,
+        };
+        assert!(client_settings_changed(&with_cert, &with_cert));
+    }
+
+    /// Serve the router on an ephemeral port and return its base URL
+    async fn serve_router(rate_limit: RateLimitConfig, max_body_bytes: usize) -> String {
+        // The default backend is a closed port, so chat completions fail fast
+        test_support::serve(build_router(
+            AppState::builder().build(),
+            &rate_limit,
+            ConcurrencyLimit::from(&ProxyConfig::default()),
+            max_body_bytes,
+        ))
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_models_rate_limit_exempts_health() {
+        let base = serve_router(
+            RateLimitConfig {
+                completions_per_second: 0,
+                models_per_second: 1,
+                key_by: RateLimitKey::Global,
+                trusted_hops: 1,
+                per_user: false,
+                users_per_key: 8,
+            },
+            1024 * 1024,
+        )
+        .await;
+        let client = reqwest::Client::new();
+
+        let first = client
+            .get(format!("{}/v1/models", base))
+            .send()
+            .await
+            .unwrap();
+        let second = client
+            .get(format!("{}/v1/models", base))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(first.status(), 200);
+        assert_eq!(second.status(), 429);
+        assert!(second.headers().contains_key("retry-after"));
+
+        for _ in 0..3 {
+            let health = client.get(format!("{}/health", base)).send().await.unwrap();
+            assert_eq!(health.status(), 200);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_completions_rate_limit_is_separate_from_models() {
+        let base = serve_router(
+            RateLimitConfig {
+                completions_per_second: 1,
+                models_per_second: 0,
+                key_by: RateLimitKey::Global,
+                trusted_hops: 1,
+                per_user: false,
+                users_per_key: 8,
+            },
+            1024 * 1024,
+        )
+        .await;
+        let client = reqwest::Client::new();
+        let body = serde_json::json!({
+            "model": "default-model",
+            "messages": [{"role": "user", "content": "hello"}]
+        });
+
+        let send = || {
+            client
+                .post(format!("{}/v1/chat/completions", base))
+                .json(&body)
+                .send()
+        };
+        assert_eq!(send().await.unwrap().status(), 502);
+        let throttled = send().await.unwrap();
+        assert_eq!(throttled.status(), 429);
+        let error: serde_json::Value = throttled.json().await.unwrap();
+        assert_eq!(error["error"]["type"], "rate_limit_error");
+
+        for _ in 0..3 {
+            let models = client
+                .get(format!("{}/v1/models", base))
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(models.status(), 200);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_head_requests_check_the_rate_limit_without_using_it() {
+        let base = serve_router(
+            RateLimitConfig {
+                completions_per_second: 1,
+                models_per_second: 0,
+                key_by: RateLimitKey::Global,
+                trusted_hops: 1,
+                per_user: false,
+                users_per_key: 8,
+            },
+            1024 * 1024,
+        )
+        .await;
+        let client = reqwest::Client::new();
+        let url = format!("{}/v1/chat/completions", base);
+        let send = || {
+            client.post(&url).json(&serde_json::json!({
+                "model": "default-model",
+                "messages": [{"role": "user", "content": "hello"}]
+            }))
+        };
+
+        // Checking leaves the budget to the request that follows
+        for _ in 0..3 {
+            let head = client.head(&url).send().await.unwrap();
+            assert_ne!(head.status(), 429);
+        }
+        assert_eq!(send().send().await.unwrap().status(), 502);
+        assert_eq!(send().send().await.unwrap().status(), 429);
+
+        let head = client.head(&url).send().await.unwrap();
+        assert_eq!(head.status(), 429);
+        assert!(head.headers().contains_key("retry-after"));
+    }
+
+    #[tokio::test]
+    async fn test_per_user_rate_limit_keys_by_user_field() {
+        let base = serve_router(
+            RateLimitConfig {
+                completions_per_second: 1,
+                models_per_second: 0,
+                key_by: RateLimitKey::Global,
+                trusted_hops: 1,
+                per_user: true,
+                users_per_key: 8,
+            },
+            1024 * 1024,
+        )
+        .await;
+        let client = reqwest::Client::new();
+        let send = |user: &str| {
+            client
+                .post(format!("{}/v1/chat/completions", base))
+                .json(&serde_json::json!({
+                    "model": "default-model",
+                    "messages": [{"role": "user", "content": "hello"}],
+                    "user": user
+                }))
+                .send()
+        };
+
+        // The body still reaches the handler, which fails on the closed port
+        assert_eq!(send("alice").await.unwrap().status(), 502);
+        assert_eq!(send("bob").await.unwrap().status(), 502);
+        assert_eq!(send("alice").await.unwrap().status(), 429);
+    }
+
+    #[tokio::test]
+    async fn test_per_user_rate_limit_is_bounded_per_key() {
+        let base = serve_router(
+            RateLimitConfig {
+                completions_per_second: 1,
+                models_per_second: 0,
+                key_by: RateLimitKey::Global,
+                trusted_hops: 1,
+                per_user: true,
+                users_per_key: 2,
+            },
+            1024 * 1024,
+        )
+        .await;
+        let client = reqwest::Client::new();
+        let send = |user: String| {
+            client
+                .post(format!("{}/v1/chat/completions", base))
+                .json(&serde_json::json!({
+                    "model": "default-model",
+                    "messages": [{"role": "user", "content": "hello"}],
+                    "user": user
+                }))
+                .send()
+        };
+
+        // A new user with every request doesn't lift the limit
+        let mut statuses = Vec::new();
+        for i in 0..4 {
+            statuses.push(send(format!("user-{}", i)).await.unwrap().status());
+        }
+        assert_eq!(statuses, [502, 502, 429, 429]);
+    }
+
+    #[tokio::test]
+    async fn test_header_rate_limit_keys_by_forwarded_client() {
+        let base = serve_router(
+            RateLimitConfig {
+                completions_per_second: 0,
+                models_per_second: 1,
+                key_by: RateLimitKey::Header,
+                trusted_hops: 1,
+                per_user: false,
+                users_per_key: 8,
+            },
+            1024 * 1024,
+        )
+        .await;
+        let client = reqwest::Client::new();
+        let models = |forwarded_for: &'static str| {
+            client
+                .get(format!("{}/v1/models", base))
+                .header("x-forwarded-for", forwarded_for)
+                .send()
+        };
+
+        assert_eq!(models("10.0.0.1").await.unwrap().status(), 200);
+        assert_eq!(models("10.0.0.2").await.unwrap().status(), 200);
+        assert_eq!(models("10.0.0.1").await.unwrap().status(), 429);
+        // A client can't get a fresh bucket by prepending addresses
+        assert_eq!(models("192.0.2.1, 10.0.0.1").await.unwrap().status(), 429);
+    }
+
+    #[tokio::test]
+    async fn test_ip_rate_limit_uses_peer_address() {
+        let base = serve_router(
+            RateLimitConfig {
+                completions_per_second: 0,
+                models_per_second: 1,
+                key_by: RateLimitKey::Ip,
+                trusted_hops: 1,
+                per_user: false,
+                users_per_key: 8,
+            },
+            1024 * 1024,
+        )
+        .await;
+        let client = reqwest::Client::new();
+
+        let first = client.get(format!("{}/v1/models", base)).send().await;
+        let second = client.get(format!("{}/v1/models", base)).send().await;
+
+        assert_eq!(first.unwrap().status(), 200);
+        assert_eq!(second.unwrap().status(), 429);
+    }
+
+    #[tokio::test]
+    async fn test_concurrency_limit_rejects_requests_over_the_limit() {
+        let backend = test_support::MockBackend::start_with_delay(
+            axum::http::StatusCode::OK,
+            serde_json::json!({ "data": { "text": "hi" } }),
+            Duration::from_millis(500),
+        )
+        .await;
+        let concurrency = ConcurrencyLimit {
+            max_requests: 1,
+            max_wait: Duration::ZERO,
+        };
+        let base = test_support::serve(build_router(
+            backend.state(""),
+            &RateLimitConfig::default(),
+            concurrency,
+            1024 * 1024,
+        ))
+        .await;
+        let client = reqwest::Client::new();
+        let body = serde_json::json!({
+            "model": "default-model",
+            "messages": [{"role": "user", "content": "hello"}]
+        });
+        let send = || {
+            client
+                .post(format!("{}/v1/chat/completions", base))
+                .json(&body)
+                .send()
+        };
+
+        let slow = tokio::spawn(send());
+        while backend.hits() == 0 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        let rejected = send().await.unwrap();
+        assert_eq!(rejected.status(), 503);
+        let error: serde_json::Value = rejected.json().await.unwrap();
+        assert_eq!(error["error"]["type"], "overloaded_error");
+
+        // Models and health checks are not counted
+        let models = client.get(format!("{}/v1/models", base)).send().await;
+        assert_eq!(models.unwrap().status(), 200);
+
+        assert_eq!(slow.await.unwrap().unwrap().status(), 200);
+        assert_eq!(send().await.unwrap().status(), 200);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_route_returns_openai_error() {
+        let base = serve_router(RateLimitConfig::default(), 1024 * 1024).await;
+        let client = reqwest::Client::new();
+
+        for request in [
+            client.get(format!("{}/v1/nonexistent", base)),
+            client.post(format!("{}/v1/completion", base)),
+        ] {
+            let response = request.send().await.unwrap();
+            assert_eq!(response.status(), 404);
+            let error: serde_json::Value = response.json().await.unwrap();
+            assert_eq!(error["error"]["message"], "Unknown endpoint");
+            assert_eq!(error["error"]["type"], "invalid_request_error");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_oversized_request_body_is_rejected() {
+        let base = serve_router(RateLimitConfig::default(), 1024).await;
+        let body = serde_json::json!({
+            "model": "default-model",
+            "messages": [{"role": "user", "content": "a".repeat(2048)}]
+        });
+
+        let response = reqwest::Client::new()
+            .post(format!("{}/v1/chat/completions", base))
+            .json(&body)
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), 413);
+    }
+
+    #[tokio::test]
+    async fn test_malformed_request_returns_openai_error() {
+        let base = serve_router(RateLimitConfig::default(), 1024 * 1024).await;
+        let client = reqwest::Client::new();
+
+        let response = client
+            .post(format!("{}/v1/chat/completions", base))
+            .json(&serde_json::json!({}))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 400);
+        let error: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(error["error"]["type"], "invalid_request_error");
+        assert_eq!(error["error"]["param"], "model");
+        assert!(error["error"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("missing field `model`"));
+
+        let response = client
+            .post(format!("{}/v1/chat/completions", base))
+            .header("content-type", "application/json")
+            .body("{not json")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 400);
+        let error: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(error["error"]["type"], "invalid_request_error");
+        assert!(error["error"]["param"].is_null());
+    }
+}
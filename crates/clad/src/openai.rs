@@ -1,3 +1,6 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
@@ -11,28 +14,43 @@ pub struct ChatCompletionRequest {
     pub messages: Vec<Message>,
     /// Temperature
     #[serde(default)]
-    pub temperature: Option<f32>,
+    pub temperature: Option<f64>,
     /// Top P
     #[serde(default)]
-    pub top_p: Option<f32>,
+    pub top_p: Option<f64>,
     /// Number of completions
     #[serde(default)]
     pub n: Option<u32>,
     /// Stream
     #[serde(default)]
     pub stream: Option<bool>,
+    /// Streaming options
+    #[serde(default)]
+    pub stream_options: Option<StreamOptions>,
     /// Stop
     #[serde(default)]
     pub stop: Option<Vec<String>>,
     /// Maximum tokens
     #[serde(default)]
     pub max_tokens: Option<u32>,
+    /// Return log probabilities of the output tokens
+    #[serde(default)]
+    pub logprobs: Option<bool>,
+    /// Number of most likely tokens to return at each position
+    #[serde(default)]
+    pub top_logprobs: Option<u32>,
+    /// Seed for deterministic sampling, on backends that support it
+    #[serde(default)]
+    pub seed: Option<i64>,
+    /// Format the reply must be given in, such as `{"type": "json_object"}`
+    #[serde(default)]
+    pub response_format: Option<ResponseFormat>,
     /// Presence penalty
     #[serde(default)]
-    pub presence_penalty: Option<f32>,
+    pub presence_penalty: Option<f64>,
     /// Frequency penalty
     #[serde(default)]
-    pub frequency_penalty: Option<f32>,
+    pub frequency_penalty: Option<f64>,
     /// User
     #[serde(default)]
     pub user: Option<String>,
@@ -54,13 +72,82 @@ pub struct Message {
     pub role: String,
     /// Content
     #[serde(default)]
-    pub content: String,
+    pub content: MessageContent,
     /// Name
     #[serde(skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,
     /// Tool calls
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_calls: Option<Vec<ToolCall>>,
+    /// ID of the tool call a `tool` message answers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+}
+
+/// Message content, either plain text or a list of content parts
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum MessageContent {
+    /// Plain text
+    Text(String),
+    /// Content parts, as sent by newer clients
+    Parts(Vec<ContentPart>),
+}
+
+impl MessageContent {
+    /// The text of the message
+    ///
+    /// Text parts are joined with newlines; other parts (such as images)
+    /// have no text and are left out.
+    pub fn as_text(&self) -> Cow<'_, str> {
+        match self {
+            Self::Text(text) => Cow::Borrowed(text),
+            Self::Parts(parts) => {
+                let texts: Vec<&str> = parts
+                    .iter()
+                    .filter_map(|part| part.text.as_deref())
+                    .collect();
+                Cow::Owned(texts.join("\n"))
+            }
+        }
+    }
+
+    /// Mutable access to every piece of text in the message
+    pub fn texts_mut(&mut self) -> Vec<&mut String> {
+        match self {
+            Self::Text(text) => vec![text],
+            Self::Parts(parts) => parts
+                .iter_mut()
+                .filter_map(|part| part.text.as_mut())
+                .collect(),
+        }
+    }
+}
+
+impl Default for MessageContent {
+    fn default() -> Self {
+        Self::Text(String::new())
+    }
+}
+
+impl From<String> for MessageContent {
+    fn from(text: String) -> Self {
+        Self::Text(text)
+    }
+}
+
+/// One part of a multi-part message content
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ContentPart {
+    /// Part type, such as "text" or "image_url"
+    #[serde(rename = "type")]
+    pub part_type: String,
+    /// Text of a "text" part
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// Remaining fields of other part types, kept as sent
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
 }
 
 /// Tool call structure for function calling
@@ -108,7 +195,7 @@ pub struct FunctionDefinition {
 
 /// OpenAI chat completion response structure
 /// This is what we need to return to Goose
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ChatCompletionResponse {
     /// ID
     pub id: String,
@@ -118,6 +205,10 @@ pub struct ChatCompletionResponse {
     pub created: i64,
     /// Model
     pub model: String,
+    /// Backend configuration that produced the response, when the backend
+    /// reports one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_fingerprint: Option<String>,
     /// Choices
     pub choices: Vec<Choice>,
     /// Usage
@@ -125,18 +216,47 @@ pub struct ChatCompletionResponse {
 }
 
 /// Choice structure
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Choice {
     /// Index
     pub index: u32,
     /// Message
     pub message: Message,
+    /// Log probabilities, when requested and returned by the backend
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logprobs: Option<Value>,
     /// Finish reason
     pub finish_reason: Option<String>,
 }
 
+/// Options for streaming responses
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct StreamOptions {
+    /// Send a final chunk with the usage of the whole request
+    #[serde(default)]
+    pub include_usage: bool,
+}
+
+/// Format requested for the reply
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ResponseFormat {
+    /// `text`, `json_object` or `json_schema`
+    #[serde(rename = "type")]
+    pub format_type: String,
+    /// Schema the reply follows, for `json_schema`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub json_schema: Option<Value>,
+}
+
+impl ResponseFormat {
+    /// Whether the reply must be valid JSON
+    pub fn requires_json(&self) -> bool {
+        matches!(self.format_type.as_str(), "json_object" | "json_schema")
+    }
+}
+
 /// Usage structure
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Usage {
     /// Prompt tokens
     pub prompt_tokens: u32,
@@ -157,8 +277,16 @@ pub struct ChatCompletionChunk {
     pub created: i64,
     /// Model
     pub model: String,
+    /// Backend configuration that produced the response, when the backend
+    /// reports one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_fingerprint: Option<String>,
     /// Choices
     pub choices: Vec<ChunkChoice>,
+    /// Usage of the whole request, only in the final chunk and only when
+    /// requested with `stream_options`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
 }
 
 /// Chunk choice structure
@@ -187,6 +315,71 @@ pub struct Delta {
     pub tool_calls: Option<Vec<ToolCall>>,
 }
 
+/// Embeddings request structure
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EmbeddingRequest {
+    /// Model ID
+    pub model: String,
+    /// Text (or texts) to embed
+    pub input: EmbeddingInput,
+    /// Additional fields that might be present
+    #[serde(flatten)]
+    pub extra: std::collections::HashMap<String, Value>,
+}
+
+/// Embeddings input: a single string or an array of strings
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum EmbeddingInput {
+    /// Single input text
+    Single(String),
+    /// Several input texts
+    Multiple(Vec<String>),
+}
+
+impl EmbeddingInput {
+    /// The input texts, in order
+    pub fn texts(&self) -> Vec<&str> {
+        match self {
+            EmbeddingInput::Single(text) => vec![text.as_str()],
+            EmbeddingInput::Multiple(texts) => texts.iter().map(String::as_str).collect(),
+        }
+    }
+}
+
+/// Embeddings response structure
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EmbeddingResponse {
+    /// Object type
+    pub object: String,
+    /// One embedding per input
+    pub data: Vec<Embedding>,
+    /// Model
+    pub model: String,
+    /// Usage
+    pub usage: EmbeddingUsage,
+}
+
+/// A single embedding vector
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Embedding {
+    /// Object type
+    pub object: String,
+    /// Embedding vector
+    pub embedding: Vec<f64>,
+    /// Index of the input this embedding belongs to
+    pub index: u32,
+}
+
+/// Token usage for an embeddings request
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EmbeddingUsage {
+    /// Prompt tokens
+    pub prompt_tokens: u32,
+    /// Total tokens
+    pub total_tokens: u32,
+}
+
 /// Models list response
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ModelsResponse {
@@ -220,16 +413,22 @@ mod tests {
             model: "gpt-4".to_string(),
             messages: vec![Message {
                 role: "user".to_string(),
-                content: "Hello".to_string(),
+                content: "Hello".to_string().into(),
                 name: None,
                 tool_calls: None,
+                tool_call_id: None,
             }],
             temperature: Some(0.8),
             top_p: None,
             n: None,
             stream: Some(false),
+            stream_options: None,
             stop: None,
             max_tokens: Some(1000),
+            logprobs: None,
+            top_logprobs: None,
+            seed: None,
+            response_format: None,
             presence_penalty: None,
             frequency_penalty: None,
             user: None,
@@ -250,6 +449,68 @@ mod tests {
         assert_eq!(deserialized.max_tokens, Some(1000));
     }
 
+    /// Test logprobs are parsed as fields rather than kept in `extra`
+    #[test]
+    fn test_chat_completion_request_logprobs() {
+        use serde_json::json;
+
+        let request: ChatCompletionRequest = serde_json::from_value(json!({
+            "model": "gpt-4",
+            "messages": [],
+            "logprobs": true,
+            "top_logprobs": 3
+        }))
+        .unwrap();
+
+        assert_eq!(request.logprobs, Some(true));
+        assert_eq!(request.top_logprobs, Some(3));
+        assert!(request.extra.is_empty());
+    }
+
+    /// Test stream_options is parsed as a field rather than kept in `extra`
+    #[test]
+    fn test_chat_completion_request_stream_options() {
+        use serde_json::json;
+
+        let request: ChatCompletionRequest = serde_json::from_value(json!({
+            "model": "gpt-4",
+            "messages": [],
+            "stream": true,
+            "stream_options": {"include_usage": true}
+        }))
+        .unwrap();
+
+        assert!(request.stream_options.unwrap().include_usage);
+        assert!(request.extra.is_empty());
+    }
+
+    /// Test response_format is parsed as a field rather than kept in `extra`
+    #[test]
+    fn test_chat_completion_request_response_format() {
+        use serde_json::json;
+
+        let request: ChatCompletionRequest = serde_json::from_value(json!({
+            "model": "gpt-4",
+            "messages": [],
+            "response_format": {"type": "json_object"}
+        }))
+        .unwrap();
+
+        let format = request.response_format.unwrap();
+        assert_eq!(format.format_type, "json_object");
+        assert!(format.requires_json());
+        assert!(request.extra.is_empty());
+        assert_eq!(
+            serde_json::to_value(&format).unwrap(),
+            json!({"type": "json_object"})
+        );
+        assert!(!ResponseFormat {
+            format_type: "text".to_string(),
+            json_schema: None
+        }
+        .requires_json());
+    }
+
     /// Test ChatCompletionRequest with extra fields
     #[test]
     fn test_chat_completion_request_with_extra_fields() {
@@ -280,9 +541,10 @@ mod tests {
     fn test_message_with_name() {
         let msg = Message {
             role: "user".to_string(),
-            content: "Hello".to_string(),
+            content: "Hello".to_string().into(),
             name: Some("John".to_string()),
             tool_calls: None,
+            tool_call_id: None,
         };
 
         let json_str = serde_json::to_string(&msg).unwrap();
@@ -297,12 +559,51 @@ mod tests {
     fn test_message_without_name() {
         let msg = Message {
             role: "assistant".to_string(),
-            content: "Hi there".to_string(),
+            content: "Hi there".to_string().into(),
             name: None,
             tool_calls: None,
+            tool_call_id: None,
         };
 
         let json_str = serde_json::to_string(&msg).unwrap();
         assert!(!json_str.contains("name"));
     }
+
+    /// Test message content accepts a string or an array of parts
+    #[test]
+    fn test_message_content_forms() {
+        let text: Message =
+            serde_json::from_str(r#"{"role": "user", "content": "hello"}"#).unwrap();
+        assert_eq!(text.content, MessageContent::Text("hello".to_string()));
+        assert_eq!(text.content.as_text(), "hello");
+
+        let parts: Message = serde_json::from_str(
+            r#"{"role": "user", "content": [
+                {"type": "text", "text": "describe"},
+                {"type": "image_url", "image_url": {"url": "https://example.com/a.png"}}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(parts.content.as_text(), "describe");
+
+        // Parts are forwarded as sent
+        let json = serde_json::to_value(&parts).unwrap();
+        assert_eq!(
+            json["content"][1]["image_url"]["url"],
+            "https://example.com/a.png"
+        );
+        assert!(json["content"][1].get("text").is_none());
+    }
+
+    /// Test embeddings input accepts a string or an array of strings
+    #[test]
+    fn test_embedding_input_forms() {
+        let single: EmbeddingRequest =
+            serde_json::from_str(r#"{"model": "embed", "input": "hello"}"#).unwrap();
+        assert_eq!(single.input.texts(), vec!["hello"]);
+
+        let multiple: EmbeddingRequest =
+            serde_json::from_str(r#"{"model": "embed", "input": ["a", "b"]}"#).unwrap();
+        assert_eq!(multiple.input.texts(), vec!["a", "b"]);
+    }
 }
@@ -0,0 +1,493 @@
+//! Rate limiting for the API routes
+//!
+//! Limits are applied per route group with `tower_governor`. By default all
+//! clients share a single budget, since clad listens on the loopback
+//! interface by default; `[proxy.rate_limit] key_by` can split it per peer IP
+//! or per client as reported by a reverse proxy, and `per_user` splits each
+//! bucket further by the `user` field of the request body, while a second
+//! limit keeps all users of a bucket within `users_per_key` times the limit.
+//! Buckets that are full again are dropped every [`EVICT_INTERVAL`], so
+//! clients that stopped sending requests don't stay in memory.
+//!
+//! HEAD requests, as sent by `c --precheck`, don't use up the budget: they
+//! are answered 429 while the last refused request of their bucket would
+//! still have to wait, see [`Refusals`].
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::body::{Body, Bytes};
+use axum::extract::{ConnectInfo, FromRequest};
+use axum::http::header::{AUTHORIZATION, RETRY_AFTER};
+use axum::http::{Method, Request, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::Router;
+use serde::Deserialize;
+use tokio::runtime::Handle;
+use tower_governor::{
+    governor::GovernorConfigBuilder, key_extractor::KeyExtractor, GovernorError, GovernorLayer,
+};
+use tracing::warn;
+
+use crate::config::{RateLimitConfig, RateLimitKey};
+use crate::provider::AppError;
+use crate::state::AppState;
+
+/// Header a reverse proxy uses to report the original client address
+const FORWARDED_FOR_HEADER: &str = "x-forwarded-for";
+
+/// How often idle buckets are dropped
+const EVICT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// `user` field of a request body, attached to the request by [`tag_user`]
+#[derive(Clone, Debug)]
+struct RequestUser(String);
+
+/// Groups requests into buckets according to `key_by`, and with `per_user`
+/// per user when the request was tagged with a [`RequestUser`]
+#[derive(Clone, Copy, Debug)]
+pub struct RequestKeyExtractor {
+    key_by: RateLimitKey,
+    trusted_hops: usize,
+    per_user: bool,
+}
+
+impl KeyExtractor for RequestKeyExtractor {
+    type Key = String;
+
+    fn extract<T>(&self, request: &Request<T>) -> Result<Self::Key, GovernorError> {
+        let key = match self.key_by {
+            RateLimitKey::Global => String::new(),
+            RateLimitKey::Ip => request
+                .extensions()
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(addr)| addr.ip().to_string())
+                .ok_or(GovernorError::UnableToExtractKey)?,
+            RateLimitKey::Header => header_key(request, self.trusted_hops),
+        };
+        match request.extensions().get::<RequestUser>() {
+            Some(RequestUser(user)) if self.per_user => {
+                Ok(format!("{}|user:{:x}", key, hash(user.as_bytes())))
+            }
+            _ => Ok(key),
+        }
+    }
+}
+
+/// Hash of `value`, so long client-chosen values are not kept as keys
+fn hash(value: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Key for `key_by = "header"`
+///
+/// The `X-Forwarded-For` address `trusted_hops` places from the right
+/// identifies the client, otherwise the `Authorization` credential does.
+/// Either is hashed, so that neither client-chosen values nor credentials
+/// are kept in memory. Requests carrying neither share one bucket.
+fn header_key<T>(request: &Request<T>, trusted_hops: usize) -> String {
+    let headers = request.headers();
+    if let Some(client) = headers
+        .get(FORWARDED_FOR_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| forwarded_client(value, trusted_hops))
+    {
+        return format!("ip:{:x}", hash(client.as_bytes()));
+    }
+
+    match headers.get(AUTHORIZATION) {
+        Some(credential) => format!("key:{:x}", hash(credential.as_bytes())),
+        None => String::new(),
+    }
+}
+
+/// Client address in an `X-Forwarded-For` value, `trusted_hops` entries
+/// from the right
+///
+/// Each proxy appends the address it got the request from, so everything
+/// left of the entry added by the outermost trusted proxy is client-chosen.
+/// With fewer entries than trusted hops, the leftmost one is used.
+fn forwarded_client(value: &str, trusted_hops: usize) -> Option<&str> {
+    let entries: Vec<&str> = value.split(',').map(str::trim).collect();
+    let index = entries.len().saturating_sub(trusted_hops.max(1));
+    entries
+        .get(index)
+        .copied()
+        .filter(|client| !client.is_empty())
+}
+
+/// Limit the routes of `router` to `per_second` requests per second
+///
+/// Bursts of up to `per_second` requests are allowed per bucket. With
+/// `per_user`, each user named in a JSON request body gets its own bucket
+/// within the one chosen by `key_by`, and all users of that bucket together
+/// are limited to `users_per_key` times `per_second`. A limit of 0 leaves
+/// the routes unlimited.
+pub fn limit(
+    router: Router<AppState>,
+    per_second: u32,
+    rate_limit: &RateLimitConfig,
+    per_user: bool,
+) -> Router<AppState> {
+    if per_second == 0 {
+        return router;
+    }
+
+    let key_extractor = RequestKeyExtractor {
+        key_by: rate_limit.key_by,
+        trusted_hops: rate_limit.trusted_hops,
+        per_user,
+    };
+    let router = with_limit(router, per_second, key_extractor);
+    if !per_user {
+        return router;
+    }
+
+    // Checked before the per-user limit, so that users made up on the fly
+    // still share one budget
+    let shared = per_second.saturating_mul(rate_limit.users_per_key.max(1));
+    let router = with_limit(
+        router,
+        shared,
+        RequestKeyExtractor {
+            per_user: false,
+            ..key_extractor
+        },
+    );
+    router.layer(middleware::from_fn(tag_user))
+}
+
+/// Methods the limiter counts; HEAD requests only look at it
+const LIMITED_METHODS: [Method; 6] = [
+    Method::GET,
+    Method::POST,
+    Method::PUT,
+    Method::PATCH,
+    Method::DELETE,
+    Method::OPTIONS,
+];
+
+/// Limit each bucket of `key_extractor` to `per_second` requests per second,
+/// in bursts of up to as many
+///
+/// An invalid limit is logged and leaves `router` unlimited.
+fn with_limit(
+    router: Router<AppState>,
+    per_second: u32,
+    key_extractor: RequestKeyExtractor,
+) -> Router<AppState> {
+    let config = GovernorConfigBuilder::default()
+        .per_nanosecond((1_000_000_000 / u64::from(per_second)).max(1))
+        .burst_size(per_second)
+        .methods(LIMITED_METHODS.to_vec())
+        .key_extractor(key_extractor)
+        .error_handler(rate_limit_error)
+        .finish();
+    let Some(config) = config else {
+        warn!(
+            "Invalid rate limit of {} requests per second, ignoring it",
+            per_second
+        );
+        return router;
+    };
+    evict_idle_buckets(config.limiter(), |limiter| limiter.retain_recent());
+
+    let refusals = Refusals::default();
+    router
+        .layer(GovernorLayer {
+            config: Arc::new(config),
+        })
+        .layer(middleware::from_fn(move |request, next| {
+            track_refusals(request, next, key_extractor, refusals.clone())
+        }))
+}
+
+/// Time until which each bucket of a limiter refuses requests, as told by
+/// the `Retry-After` of the last refusal
+#[derive(Clone, Debug, Default)]
+struct Refusals(Arc<Mutex<HashMap<String, Instant>>>);
+
+impl Refusals {
+    /// Remember that `key` is refused for `retry_after` seconds
+    ///
+    /// The limiter rounds its wait down to whole seconds, so a second is
+    /// added. Buckets that accept requests again are forgotten.
+    fn record(&self, key: String, retry_after: u64) {
+        let now = Instant::now();
+        let mut refusals = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        refusals.retain(|_, until| *until > now);
+        refusals.insert(
+            key,
+            now + Duration::from_secs(retry_after.saturating_add(1)),
+        );
+    }
+
+    /// Seconds `key` is still refused for, rounded up
+    fn remaining(&self, key: &str) -> Option<u64> {
+        let refusals = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        let left = refusals.get(key)?.checked_duration_since(Instant::now())?;
+        (!left.is_zero()).then(|| left.as_secs() + u64::from(left.subsec_nanos() > 0))
+    }
+}
+
+/// Answer HEAD requests from `refusals`, and record the requests the
+/// limiter refuses
+///
+/// A HEAD request is answered 429 while its bucket refuses requests and is
+/// passed on otherwise, without taking from the budget.
+async fn track_refusals(
+    request: Request<Body>,
+    next: Next,
+    key_extractor: RequestKeyExtractor,
+    refusals: Refusals,
+) -> Response {
+    let Ok(key) = key_extractor.extract(&request) else {
+        return next.run(request).await;
+    };
+    if request.method() == Method::HEAD {
+        if let Some(retry_after) = refusals.remaining(&key) {
+            return AppError::RateLimited {
+                retry_after: Some(retry_after.to_string()),
+            }
+            .into_response();
+        }
+        return next.run(request).await;
+    }
+
+    let response = next.run(request).await;
+    if response.status() == StatusCode::TOO_MANY_REQUESTS {
+        if let Some(retry_after) = response
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse().ok())
+        {
+            refusals.record(key, retry_after);
+        }
+    }
+    response
+}
+
+/// Call `retain` on `limiter` every [`EVICT_INTERVAL`], for as long as the
+/// limiter is in use
+///
+/// Outside of a Tokio runtime, as in unit tests, nothing is evicted.
+fn evict_idle_buckets<T: Send + Sync + 'static>(limiter: &Arc<T>, retain: fn(&T)) {
+    let Ok(runtime) = Handle::try_current() else {
+        return;
+    };
+    let limiter = Arc::downgrade(limiter);
+    runtime.spawn(async move {
+        let mut interval = tokio::time::interval(EVICT_INTERVAL);
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            let Some(limiter) = limiter.upgrade() else {
+                break;
+            };
+            retain(&limiter);
+        }
+    });
+}
+
+/// The only request field `tag_user` looks at
+#[derive(Deserialize)]
+struct UserField {
+    user: Option<String>,
+}
+
+/// Tag the request with the `user` field of its JSON body, if it has one
+///
+/// The body is buffered to read it, within the body size limit, and handed
+/// on unchanged. Bodies that are not JSON are left for the handler to
+/// reject.
+async fn tag_user(request: Request<Body>, next: Next) -> Response {
+    let (parts, body) = request.into_parts();
+    let bytes = match Bytes::from_request(Request::from_parts(parts.clone(), body), &()).await {
+        Ok(bytes) => bytes,
+        Err(rejection) => {
+            return AppError::InvalidRequest {
+                status: rejection.status(),
+                message: rejection.body_text(),
+                param: None,
+            }
+            .into_response()
+        }
+    };
+
+    let mut request = Request::from_parts(parts, Body::from(bytes.clone()));
+    if let Some(user) = serde_json::from_slice::<UserField>(&bytes)
+        .ok()
+        .and_then(|field| field.user)
+        .filter(|user| !user.is_empty())
+    {
+        request.extensions_mut().insert(RequestUser(user));
+    }
+    next.run(request).await
+}
+
+/// Answer throttled requests with the same error body as backend rate limits
+fn rate_limit_error(error: GovernorError) -> Response {
+    match error {
+        GovernorError::TooManyRequests { wait_time, .. } => AppError::RateLimited {
+            retry_after: Some(wait_time.to_string()),
+        }
+        .into_response(),
+        other => AppError::InternalError(other.to_string()).into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(headers: &[(&str, &str)]) -> Request<()> {
+        let mut builder = Request::builder();
+        for (name, value) in headers {
+            builder = builder.header(*name, *value);
+        }
+        builder.body(()).unwrap()
+    }
+
+    fn key(key_by: RateLimitKey, request: &Request<()>) -> Option<String> {
+        RequestKeyExtractor {
+            key_by,
+            trusted_hops: 1,
+            per_user: true,
+        }
+        .extract(request)
+        .ok()
+    }
+
+    #[test]
+    fn test_refusals_expire() {
+        let refusals = Refusals::default();
+        assert_eq!(refusals.remaining("a"), None);
+
+        refusals.record("a".to_string(), 2);
+        assert_eq!(refusals.remaining("a"), Some(3));
+        assert_eq!(refusals.remaining("b"), None);
+
+        refusals
+            .0
+            .lock()
+            .unwrap()
+            .insert("a".to_string(), Instant::now());
+        assert_eq!(refusals.remaining("a"), None);
+        refusals.record("b".to_string(), 0);
+        assert!(!refusals.0.lock().unwrap().contains_key("a"));
+    }
+
+    #[test]
+    fn test_global_key_is_shared() {
+        let a = request(&[("x-forwarded-for", "10.0.0.1")]);
+        let b = request(&[("x-forwarded-for", "10.0.0.2")]);
+
+        assert_eq!(
+            key(RateLimitKey::Global, &a).unwrap(),
+            key(RateLimitKey::Global, &b).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_ip_key_uses_peer_address() {
+        let mut request = request(&[("x-forwarded-for", "10.0.0.1")]);
+        assert!(key(RateLimitKey::Ip, &request).is_none());
+
+        request
+            .extensions_mut()
+            .insert(ConnectInfo(SocketAddr::from(([192, 168, 1, 7], 40000))));
+
+        assert_eq!(key(RateLimitKey::Ip, &request).unwrap(), "192.168.1.7");
+    }
+
+    #[test]
+    fn test_header_key_uses_hashed_forwarded_address() {
+        let forwarded = |value: &str| {
+            key(
+                RateLimitKey::Header,
+                &request(&[("x-forwarded-for", value), ("authorization", "Bearer abc")]),
+            )
+            .unwrap()
+        };
+
+        let client = forwarded("172.16.0.1");
+        assert!(client.starts_with("ip:"));
+        assert!(!client.contains("172.16.0.1"));
+
+        // Entries the client wrote itself don't change its bucket
+        assert_eq!(forwarded(" 10.0.0.1 , 172.16.0.1"), client);
+        assert_eq!(forwarded("spoofed-1, spoofed-2, 172.16.0.1"), client);
+        assert_ne!(forwarded("172.16.0.1, 10.0.0.1"), client);
+    }
+
+    #[test]
+    fn test_forwarded_client_skips_trusted_hops() {
+        let value = "198.51.100.7, 10.0.0.1, 10.0.0.2";
+
+        assert_eq!(forwarded_client(value, 1), Some("10.0.0.2"));
+        assert_eq!(forwarded_client(value, 2), Some("10.0.0.1"));
+        assert_eq!(forwarded_client(value, 3), Some("198.51.100.7"));
+        assert_eq!(forwarded_client(value, 5), Some("198.51.100.7"));
+        assert_eq!(forwarded_client(value, 0), Some("10.0.0.2"));
+        assert_eq!(forwarded_client("10.0.0.1, ", 1), None);
+        assert_eq!(forwarded_client("", 1), None);
+    }
+
+    #[test]
+    fn test_header_key_falls_back_to_credential() {
+        let alice = key(
+            RateLimitKey::Header,
+            &request(&[("authorization", "Bearer a")]),
+        )
+        .unwrap();
+        let bob = key(
+            RateLimitKey::Header,
+            &request(&[("authorization", "Bearer b")]),
+        )
+        .unwrap();
+
+        assert!(alice.starts_with("key:"));
+        assert!(!alice.contains("Bearer"));
+        assert_ne!(alice, bob);
+        assert_eq!(key(RateLimitKey::Header, &request(&[])).unwrap(), "");
+    }
+
+    #[test]
+    fn test_user_splits_the_bucket() {
+        let tagged = |user: &str| {
+            let mut request = request(&[("x-forwarded-for", "10.0.0.1")]);
+            request
+                .extensions_mut()
+                .insert(RequestUser(user.to_string()));
+            key(RateLimitKey::Header, &request).unwrap()
+        };
+
+        let alice = tagged("alice");
+        assert!(alice.starts_with("ip:"));
+        assert!(alice.contains("|user:"));
+        assert!(!alice.contains("alice"));
+        assert_ne!(alice, tagged("bob"));
+        assert_eq!(alice, tagged("alice"));
+
+        // The limit shared by all users of the bucket ignores the tag
+        let mut request = request(&[("x-forwarded-for", "10.0.0.1")]);
+        let untagged = key(RateLimitKey::Header, &request).unwrap();
+        request
+            .extensions_mut()
+            .insert(RequestUser("alice".to_string()));
+        let shared = RequestKeyExtractor {
+            key_by: RateLimitKey::Header,
+            trusted_hops: 1,
+            per_user: false,
+        };
+        assert_eq!(shared.extract(&request).unwrap(), untagged);
+    }
+}
@@ -0,0 +1,214 @@
+//! Readiness of the proxy to serve requests
+//!
+//! `/health` only reports that the process is up. `/ready` answers 200 once
+//! the backend has been reached, by a request, the startup check or a probe
+//! made by `/ready` itself, so orchestrators route traffic only to a proxy
+//! that can serve it. `[proxy] ready_failure_threshold` backend failures in
+//! a row mark the proxy not ready again. The state is kept across reloads.
+//! `/ready` probes the backend one request at a time, at most once every
+//! [`PROBE_INTERVAL`], so frequent readiness checks don't flood it.
+
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::time::{Duration, Instant};
+
+use reqwest::StatusCode;
+use tokio::sync::Mutex;
+use tracing::{debug, info, warn};
+
+use crate::startup_check::{self, CheckOutcome};
+use crate::state::Snapshot;
+
+/// How long `/ready` waits for each backend endpoint to answer its probe
+pub const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Shortest time between two probes made by `/ready`
+pub const PROBE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Whether the backend has been reached, and how often it has failed since
+#[derive(Debug, Default)]
+pub struct Readiness {
+    /// Set by a backend answer, cleared after too many failures
+    ready: AtomicBool,
+    /// Backend failures since the last answer
+    failures: AtomicU32,
+    /// When the last probe started; held while a probe is in progress
+    last_probe: Mutex<Option<Instant>>,
+}
+
+impl Readiness {
+    /// Whether the proxy is ready to serve requests
+    pub fn is_ready(&self) -> bool {
+        self.ready.load(Ordering::Relaxed)
+    }
+
+    /// Note that the backend answered and accepted the credentials
+    pub fn record_success(&self) {
+        self.failures.store(0, Ordering::Relaxed);
+        if !self.ready.swap(true, Ordering::Relaxed) {
+            info!("Backend reachable, proxy is ready");
+        }
+    }
+
+    /// Note that a backend request failed
+    ///
+    /// The proxy stops being ready after `threshold` failures in a row, or
+    /// never when `threshold` is 0.
+    pub fn record_failure(&self, threshold: u32) {
+        let failures = self
+            .failures
+            .fetch_add(1, Ordering::Relaxed)
+            .saturating_add(1);
+        if threshold > 0 && failures >= threshold && self.ready.swap(false, Ordering::Relaxed) {
+            warn!(failures, "Backend failing, proxy is no longer ready");
+        }
+    }
+
+    /// Note the status of a backend answer
+    ///
+    /// Server errors and rejected credentials count as failures.
+    pub fn record_status(&self, status: StatusCode, threshold: u32) {
+        match status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => self.record_failure(threshold),
+            status if status.is_server_error() => self.record_failure(threshold),
+            _ => self.record_success(),
+        }
+    }
+}
+
+/// Probe the backend endpoints in order until one accepts the credentials
+///
+/// Used by `/ready` while the proxy is not ready, so that it recovers even
+/// when no traffic is routed to it. While another probe is in progress, or
+/// within [`PROBE_INTERVAL`] of the last one, nothing is sent and the state
+/// is left as it is.
+pub async fn probe_backend(snapshot: &Snapshot) {
+    let Ok(mut last_probe) = snapshot.readiness.last_probe.try_lock() else {
+        debug!("Backend probe already in progress");
+        return;
+    };
+    if last_probe.is_some_and(|last| last.elapsed() < PROBE_INTERVAL) {
+        debug!("Backend probed recently, not probing again yet");
+        return;
+    }
+    *last_probe = Some(Instant::now());
+
+    for endpoint in snapshot.config.backend.endpoints() {
+        let outcome = tokio::time::timeout(
+            PROBE_TIMEOUT,
+            startup_check::probe(&snapshot.client, endpoint),
+        )
+        .await;
+        if let Ok(CheckOutcome::Ok(_)) = outcome {
+            snapshot.readiness.record_success();
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::AppState;
+    use crate::test_support::{serve, MockBackend};
+    use axum::routing::get;
+    use axum::Router;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_not_ready_until_success() {
+        let readiness = Readiness::default();
+        assert!(!readiness.is_ready());
+
+        readiness.record_failure(3);
+        assert!(!readiness.is_ready());
+
+        readiness.record_success();
+        assert!(readiness.is_ready());
+    }
+
+    #[test]
+    fn test_sustained_failures_clear_readiness() {
+        let readiness = Readiness::default();
+        readiness.record_success();
+
+        readiness.record_failure(3);
+        readiness.record_failure(3);
+        assert!(readiness.is_ready());
+        readiness.record_failure(3);
+        assert!(!readiness.is_ready());
+
+        // A success resets the count
+        readiness.record_success();
+        readiness.record_failure(3);
+        readiness.record_failure(3);
+        assert!(readiness.is_ready());
+    }
+
+    #[test]
+    fn test_zero_threshold_stays_ready() {
+        let readiness = Readiness::default();
+        readiness.record_success();
+
+        for _ in 0..10 {
+            readiness.record_failure(0);
+        }
+
+        assert!(readiness.is_ready());
+    }
+
+    #[test]
+    fn test_record_status() {
+        let readiness = Readiness::default();
+
+        readiness.record_status(StatusCode::BAD_REQUEST, 1);
+        assert!(readiness.is_ready());
+        readiness.record_status(StatusCode::UNAUTHORIZED, 1);
+        assert!(!readiness.is_ready());
+        readiness.record_status(StatusCode::OK, 1);
+        readiness.record_status(StatusCode::BAD_GATEWAY, 1);
+        assert!(!readiness.is_ready());
+    }
+
+    #[tokio::test]
+    async fn test_probes_are_spaced_out() {
+        let probes = Arc::new(AtomicUsize::new(0));
+        let counter = probes.clone();
+        let url = serve(Router::new().route(
+            "/",
+            get(move || {
+                counter.fetch_add(1, Ordering::Relaxed);
+                async {
+                    tokio::time::sleep(Duration::from_millis(200)).await;
+                    StatusCode::UNAUTHORIZED
+                }
+            }),
+        ))
+        .await;
+        let snapshot = AppState::builder().endpoint(&url).build().snapshot();
+
+        // Probes running at the same time and probes right after one are
+        // answered from the current state
+        tokio::join!(
+            probe_backend(&snapshot),
+            probe_backend(&snapshot),
+            probe_backend(&snapshot)
+        );
+        probe_backend(&snapshot).await;
+
+        assert_eq!(probes.load(Ordering::Relaxed), 1);
+        assert!(!snapshot.readiness.is_ready());
+    }
+
+    #[tokio::test]
+    async fn test_probe_backend() {
+        let snapshot = AppState::builder().build().snapshot();
+        probe_backend(&snapshot).await;
+        assert!(!snapshot.readiness.is_ready());
+
+        let backend = MockBackend::replying("hi").await;
+        let snapshot = backend.state("").snapshot();
+        probe_backend(&snapshot).await;
+        assert!(snapshot.readiness.is_ready());
+    }
+}
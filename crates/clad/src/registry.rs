@@ -0,0 +1,259 @@
+//! Backend providers and the registry used to select them by name
+//!
+//! A provider knows how to turn an OpenAI chat completion request into the
+//! payload its backend expects, and how to read the reply back. Providers
+//! register a factory in [`ProviderRegistry`] and are selected with the
+//! `[backend] provider` setting.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use futures::future::{self, BoxFuture};
+use reqwest::header::HeaderMap;
+use serde_json::Value;
+
+use crate::azure_openai::AzureOpenAiProvider;
+use crate::config::BackendConfig;
+use crate::echo::EchoProvider;
+use crate::openai::{ChatCompletionRequest, EmbeddingRequest, EmbeddingResponse};
+use crate::provider::{bearer_auth_headers, AppError, BackendReply, RhelLightspeedProvider};
+use crate::state::Snapshot;
+
+/// A backend the proxy can forward chat completions to
+pub trait Provider: Debug + Send + Sync {
+    /// Name used to select the provider in `[backend] provider`
+    fn name(&self) -> &'static str;
+
+    /// Build the backend payload for a chat completion request
+    fn transform_request(&self, request: &ChatCompletionRequest, backend: &BackendConfig) -> Value;
+
+    /// Check that a backend response has the shape the provider reads
+    ///
+    /// Called on every complete backend response, after the post hooks and
+    /// before `extract_replies`, so errors about missing or mistyped fields
+    /// read the same for every provider.
+    fn validate_response(
+        &self,
+        _backend_response: &Value,
+        _backend: &BackendConfig,
+    ) -> Result<(), AppError> {
+        Ok(())
+    }
+
+    /// Extract the assistant reply from a backend response
+    fn extract_reply(
+        &self,
+        backend_response: &Value,
+        backend: &BackendConfig,
+    ) -> Result<BackendReply, AppError>;
+
+    /// Extract one reply per choice from a backend response
+    fn extract_replies(
+        &self,
+        backend_response: &Value,
+        backend: &BackendConfig,
+    ) -> Result<Vec<BackendReply>, AppError> {
+        self.extract_reply(backend_response, backend)
+            .map(|reply| vec![reply])
+    }
+
+    /// Whether the backend itself answers requests for several completions
+    /// (`n` above 1); other providers follow `[backend] multiple_completions`
+    fn supports_n(&self) -> bool {
+        false
+    }
+
+    /// Text carried by one line of a backend response streamed as
+    /// newline-delimited JSON, or `None` for a line without any
+    ///
+    /// Providers whose backend never streams keep the default, which fails
+    /// such responses.
+    fn parse_stream_chunk(
+        &self,
+        _chunk: &Value,
+        _backend: &BackendConfig,
+    ) -> Result<Option<String>, AppError> {
+        Err(AppError::TransformError(format!(
+            "The {} provider can't read streamed backend responses",
+            self.name()
+        )))
+    }
+
+    /// Check the `[backend]` settings the provider relies on
+    fn validate(&self, _backend: &BackendConfig) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// URL chat completion payloads are posted to on `endpoint`
+    fn completions_url(&self, endpoint: &str, _backend: &BackendConfig) -> String {
+        endpoint.to_string()
+    }
+
+    /// Whether requests are sent to a backend
+    ///
+    /// Providers that answer on their own return `false`: they need neither
+    /// endpoints nor `[backend.auth]`, and their replies are read with
+    /// `extract_replies` from the payload built by `transform_request`.
+    fn uses_backend(&self) -> bool {
+        true
+    }
+
+    /// Request embeddings for `request`, already redacted
+    ///
+    /// Providers whose backend has no embeddings API keep the default, which
+    /// answers 501 Not Implemented; the others usually forward to
+    /// `[backend] embeddings_endpoint` with
+    /// [`forward_embeddings`](crate::provider::forward_embeddings).
+    fn embed<'a>(
+        &'a self,
+        _snapshot: &'a Snapshot,
+        _request: EmbeddingRequest,
+        _request_id: &'a str,
+    ) -> BoxFuture<'a, Result<EmbeddingResponse, AppError>> {
+        Box::pin(future::ready(Err(AppError::NotImplemented(format!(
+            "The {} provider does not support embeddings",
+            self.name()
+        )))))
+    }
+
+    /// Headers carrying the `[backend.auth]` token on every request, a
+    /// bearer `Authorization` header by default
+    fn token_headers(&self, token: &str) -> Result<HeaderMap, Box<dyn Error>> {
+        bearer_auth_headers(token)
+    }
+}
+
+/// Creates a provider instance
+pub type ProviderFactory = fn() -> Arc<dyn Provider>;
+
+/// Providers available to the proxy, by name
+#[derive(Debug, Default)]
+pub struct ProviderRegistry {
+    factories: BTreeMap<&'static str, ProviderFactory>,
+}
+
+impl ProviderRegistry {
+    /// Create a registry holding the providers shipped with clad
+    pub fn with_builtin() -> Self {
+        let mut registry = Self::default();
+        registry.register(|| Arc::new(RhelLightspeedProvider));
+        registry.register(|| Arc::new(AzureOpenAiProvider));
+        registry.register(|| Arc::new(EchoProvider));
+        registry
+    }
+
+    /// Register a provider under the name it reports
+    ///
+    /// Registering a name twice replaces the earlier factory.
+    pub fn register(&mut self, factory: ProviderFactory) {
+        let name = factory().name();
+        self.factories.insert(name, factory);
+    }
+
+    /// Names of all registered providers, sorted
+    pub fn names(&self) -> Vec<&'static str> {
+        self.factories.keys().copied().collect()
+    }
+
+    /// Create the provider registered under `name`
+    pub fn create(&self, name: &str) -> Result<Arc<dyn Provider>, String> {
+        self.factories
+            .get(name)
+            .map(|factory| factory())
+            .ok_or_else(|| {
+                format!(
+                    "Unknown provider '{}'. Available providers: {}",
+                    name,
+                    self.names().join(", ")
+                )
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[derive(Debug)]
+    struct DummyProvider;
+
+    impl Provider for DummyProvider {
+        fn name(&self) -> &'static str {
+            "dummy"
+        }
+
+        fn transform_request(
+            &self,
+            _request: &ChatCompletionRequest,
+            _backend: &BackendConfig,
+        ) -> Value {
+            json!({})
+        }
+
+        fn extract_reply(
+            &self,
+            _backend_response: &Value,
+            _backend: &BackendConfig,
+        ) -> Result<BackendReply, AppError> {
+            Ok(BackendReply {
+                text: "dummy".to_string(),
+                tool_calls: None,
+                logprobs: None,
+                system_fingerprint: None,
+            })
+        }
+    }
+
+    #[test]
+    fn test_builtin_providers() {
+        let registry = ProviderRegistry::with_builtin();
+
+        assert_eq!(
+            registry.names(),
+            vec!["azure_openai", "echo", "rhel_lightspeed"]
+        );
+        assert_eq!(
+            registry.create("rhel_lightspeed").unwrap().name(),
+            "rhel_lightspeed"
+        );
+    }
+
+    #[test]
+    fn test_registered_provider_is_resolvable() {
+        let mut registry = ProviderRegistry::with_builtin();
+        registry.register(|| Arc::new(DummyProvider));
+
+        let provider = registry.create("dummy").unwrap();
+
+        assert_eq!(provider.name(), "dummy");
+        let backend: BackendConfig = toml::from_str(
+            r#"
+            endpoint = "http://localhost:9000"
+
+            [auth]
+            token = "secret"
+        "#,
+        )
+        .unwrap();
+        assert_eq!(
+            provider.extract_reply(&json!({}), &backend).unwrap().text,
+            "dummy"
+        );
+    }
+
+    #[test]
+    fn test_unknown_provider_lists_registered_names() {
+        let mut registry = ProviderRegistry::with_builtin();
+        registry.register(|| Arc::new(DummyProvider));
+
+        let err = registry.create("missing").unwrap_err();
+
+        assert_eq!(
+            err,
+            "Unknown provider 'missing'. Available providers: azure_openai, dummy, echo, rhel_lightspeed"
+        );
+    }
+}
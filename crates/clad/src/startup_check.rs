@@ -0,0 +1,163 @@
+//! Backend self-test run at startup
+//!
+//! With `[backend] startup_check` enabled, CLAD sends one authenticated
+//! request to each backend endpoint right after startup and logs whether the
+//! TLS handshake and authentication succeeded. Connection and TLS failures
+//! are retried with exponential backoff, since the backend or its
+//! certificates may still be coming up. The check never stops the service,
+//! but an endpoint accepting the credentials marks the proxy ready.
+
+use std::error::Error;
+use std::sync::Arc;
+use std::time::Duration;
+
+use reqwest::StatusCode;
+use tokio::time::sleep;
+use tracing::{error, info, warn};
+
+use crate::readiness::Readiness;
+
+/// Attempts per endpoint before giving up on a connection failure
+const MAX_ATTEMPTS: u32 = 5;
+
+/// Delay before the first retry, doubled on each retry
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Result of probing one endpoint
+#[derive(Debug, PartialEq)]
+pub enum CheckOutcome {
+    /// The backend answered and accepted the credentials
+    Ok(StatusCode),
+    /// The backend answered but rejected the credentials
+    Rejected(StatusCode),
+    /// No answer, the last connection or TLS error is included
+    Unreachable(String),
+}
+
+/// Probe every endpoint and log the outcome
+pub async fn run(client: reqwest::Client, endpoints: Vec<String>, readiness: Arc<Readiness>) {
+    for endpoint in &endpoints {
+        match check_endpoint(&client, endpoint, INITIAL_BACKOFF).await {
+            CheckOutcome::Ok(status) => {
+                info!(endpoint, %status, "Startup check: backend auth OK");
+                readiness.record_success();
+            }
+            CheckOutcome::Rejected(status) => {
+                error!(
+                    endpoint,
+                    %status, "Startup check: backend rejected the configured credentials"
+                );
+            }
+            CheckOutcome::Unreachable(e) => {
+                error!(
+                    endpoint,
+                    "Startup check: backend unreachable after {} attempts: {}", MAX_ATTEMPTS, e
+                );
+            }
+        }
+    }
+}
+
+/// Probe `endpoint`, retrying connection failures with exponential backoff
+///
+/// Any HTTP response means the TLS handshake (including the client
+/// certificate) succeeded. Only 401 and 403 are treated as rejected
+/// credentials, since the probe is not a valid request for the backend API.
+pub async fn check_endpoint(
+    client: &reqwest::Client,
+    endpoint: &str,
+    initial_backoff: Duration,
+) -> CheckOutcome {
+    let mut last_error = String::new();
+
+    for attempt in 0..MAX_ATTEMPTS {
+        if attempt > 0 {
+            let delay = initial_backoff.saturating_mul(1 << (attempt - 1));
+            warn!(
+                endpoint,
+                attempt, "Startup check failed, retrying in {:?}: {}", delay, last_error
+            );
+            sleep(delay).await;
+        }
+
+        match probe(client, endpoint).await {
+            CheckOutcome::Unreachable(e) => last_error = e,
+            outcome => return outcome,
+        }
+    }
+
+    CheckOutcome::Unreachable(last_error)
+}
+
+/// Probe `endpoint` once, without retrying
+pub async fn probe(client: &reqwest::Client, endpoint: &str) -> CheckOutcome {
+    match client.get(endpoint).send().await {
+        Ok(response) => match response.status() {
+            status @ (StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN) => {
+                CheckOutcome::Rejected(status)
+            }
+            status => CheckOutcome::Ok(status),
+        },
+        Err(e) => CheckOutcome::Unreachable(error_chain(&e)),
+    }
+}
+
+/// Format an error with all its sources, so the underlying TLS or I/O error
+/// is not hidden behind reqwest's generic message
+fn error_chain(e: &dyn Error) -> String {
+    let mut message = e.to_string();
+    let mut source = e.source();
+    while let Some(cause) = source {
+        message.push_str(": ");
+        message.push_str(&cause.to_string());
+        source = cause.source();
+    }
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{serve, MockBackend};
+    use axum::Router;
+
+    #[tokio::test]
+    async fn test_reachable_backend_is_ok() {
+        let backend = MockBackend::replying("hi").await;
+        let client = reqwest::Client::new();
+
+        // The mock only accepts POST, any answer still proves the handshake
+        let outcome = check_endpoint(&client, &backend.url, Duration::ZERO).await;
+
+        assert_eq!(outcome, CheckOutcome::Ok(StatusCode::METHOD_NOT_ALLOWED));
+    }
+
+    #[tokio::test]
+    async fn test_rejected_credentials() {
+        let client = reqwest::Client::new();
+
+        for status in [StatusCode::UNAUTHORIZED, StatusCode::FORBIDDEN] {
+            let url = serve(Router::new().fallback(move || async move { status })).await;
+
+            assert_eq!(
+                check_endpoint(&client, &url, Duration::ZERO).await,
+                CheckOutcome::Rejected(status)
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_backend_reports_error() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+        let client = reqwest::Client::new();
+
+        let outcome = check_endpoint(&client, &url, Duration::ZERO).await;
+
+        match outcome {
+            CheckOutcome::Unreachable(e) => assert!(e.contains("error sending request"), "{}", e),
+            other => panic!("Expected unreachable, got {:?}", other),
+        }
+    }
+}
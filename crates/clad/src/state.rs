@@ -1,15 +1,355 @@
 // Library interface for clad-redux
 // This allows integration tests and external crates to use our modules
 
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard, RwLock};
 
+use crate::admin::RecentRequests;
+use crate::cache::ResponseCache;
 use crate::config;
+use crate::hooks::{self, Hook};
+use crate::provider::create_authenticated_client;
+use crate::readiness::Readiness;
+use crate::registry::{Provider, ProviderRegistry};
 
 /// Application state shared across handlers
+///
+/// The configuration and HTTP client live behind a lock so they can be
+/// swapped together when the configuration is reloaded (SIGHUP).
 #[derive(Clone, Debug)]
 pub struct AppState {
-    /// Configuration   
+    /// Current configuration and client, replaced atomically on reload
+    current: Arc<RwLock<Arc<Snapshot>>>,
+    /// Providers a request may select with the `X-CLA-Provider` header
+    registry: Arc<ProviderRegistry>,
+}
+
+/// Configuration and HTTP client in effect for a request
+///
+/// Handlers take a snapshot once per request so a concurrent reload never
+/// mixes settings from two different configurations.
+#[derive(Debug)]
+pub struct Snapshot {
+    /// Configuration
     pub config: Arc<config::Config>,
     /// HTTP client for backend requests
     pub client: reqwest::Client,
+    /// Response cache, when enabled; a reload starts with an empty cache
+    pub cache: Option<ResponseCache>,
+    /// Provider selected by `[backend] provider`
+    pub provider: Arc<dyn Provider>,
+    /// Hooks from `[[backend.hooks]]`, in order
+    pub hooks: Vec<Box<dyn Hook>>,
+    /// Recent request summaries, when the admin endpoint is enabled; a
+    /// reload starts with an empty buffer
+    pub recent: Option<Arc<RecentRequests>>,
+    /// Whether the backend has been reached; carried over on reload
+    pub readiness: Arc<Readiness>,
+    /// Snapshots for the other providers requests selected, by name
+    provider_overrides: Mutex<HashMap<&'static str, Arc<Snapshot>>>,
+}
+
+impl Snapshot {
+    fn new(
+        config: config::Config,
+        client: reqwest::Client,
+        provider: Arc<dyn Provider>,
+        readiness: Arc<Readiness>,
+    ) -> Self {
+        let cache = config
+            .proxy
+            .cache
+            .enabled
+            .then(|| ResponseCache::new(&config.proxy.cache));
+        let hooks = hooks::build(&config.backend.hooks);
+        let recent = config
+            .proxy
+            .admin
+            .enabled
+            .then(|| Arc::new(RecentRequests::new(&config.proxy.admin)));
+        Self {
+            config: Arc::new(config),
+            client,
+            cache,
+            provider,
+            hooks,
+            recent,
+            readiness,
+            provider_overrides: Mutex::default(),
+        }
+    }
+
+    /// Snapshot sending requests through the provider registered as `name`
+    ///
+    /// Providers that use a backend are sent to the endpoints, with the
+    /// credentials, of their `[backend.providers.<name>]` table, and can't be
+    /// picked without one. Built on first use, with a client authenticating
+    /// the way that provider expects, and kept until the next reload. It
+    /// shares the recent request summaries, but has its own response cache
+    /// so answers from one provider are never served for another, and its
+    /// own readiness so failures of its backend don't make `/ready` fail.
+    pub fn with_provider(
+        self: &Arc<Self>,
+        registry: &ProviderRegistry,
+        name: &str,
+    ) -> Result<Arc<Snapshot>, String> {
+        if name == self.provider.name() {
+            return Ok(self.clone());
+        }
+        let provider = registry.create(name)?;
+        if let Some(snapshot) = self.overrides().get(provider.name()) {
+            return Ok(snapshot.clone());
+        }
+
+        let mut config = (*self.config).clone();
+        if provider.uses_backend() {
+            config.backend = self
+                .config
+                .backend
+                .for_provider(provider.name())
+                .ok_or_else(|| {
+                    format!(
+                        "Provider '{}' has no [backend.providers.{}] configuration",
+                        name,
+                        provider.name()
+                    )
+                })?;
+            if config.backend.endpoints().is_empty() {
+                return Err(format!(
+                    "Provider '{}' has no endpoint in [backend.providers.{}]",
+                    name,
+                    provider.name()
+                ));
+            }
+        }
+        provider.validate(&config.backend)?;
+        // Built without holding the lock, so requests for providers already
+        // set up don't wait on it; the first snapshot stored wins
+        let client = create_authenticated_client(&config, provider.as_ref())
+            .map_err(|e| format!("Provider '{}' can't be used: {}", name, e))?;
+        let mut snapshot = Snapshot::new(config, client, provider.clone(), Arc::default());
+        snapshot.recent = self.recent.clone();
+        Ok(self
+            .overrides()
+            .entry(provider.name())
+            .or_insert_with(|| Arc::new(snapshot))
+            .clone())
+    }
+
+    fn overrides(&self) -> MutexGuard<'_, HashMap<&'static str, Arc<Snapshot>>> {
+        self.provider_overrides
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl AppState {
+    /// Create the shared state from the initial configuration, client and
+    /// provider, with the providers of `registry` available to requests
+    pub fn new(
+        config: config::Config,
+        client: reqwest::Client,
+        provider: Arc<dyn Provider>,
+        registry: Arc<ProviderRegistry>,
+    ) -> Self {
+        Self {
+            current: Arc::new(RwLock::new(Arc::new(Snapshot::new(
+                config,
+                client,
+                provider,
+                Arc::default(),
+            )))),
+            registry,
+        }
+    }
+
+    /// Providers a request may select
+    pub fn registry(&self) -> &ProviderRegistry {
+        &self.registry
+    }
+
+    /// Get the configuration and client currently in effect
+    pub fn snapshot(&self) -> Arc<Snapshot> {
+        self.current
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    /// Start building a state for a test
+    #[cfg(test)]
+    pub fn builder() -> AppStateBuilder {
+        AppStateBuilder::default()
+    }
+
+    /// Atomically replace the configuration, client and provider
+    pub fn replace(
+        &self,
+        config: config::Config,
+        client: reqwest::Client,
+        provider: Arc<dyn Provider>,
+    ) {
+        let readiness = self.snapshot().readiness.clone();
+        let snapshot = Arc::new(Snapshot::new(config, client, provider, readiness));
+        *self
+            .current
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = snapshot;
+    }
+}
+
+/// Builds an [`AppState`] for tests, overriding only what a test cares about
+///
+/// By default the backend is `http://127.0.0.1:1`, where nothing listens,
+/// with bearer token `secret`. The provider is the one `[backend] provider`
+/// names, and the client is the one the proxy would build for it.
+#[cfg(test)]
+#[derive(Debug)]
+pub struct AppStateBuilder {
+    endpoint: String,
+    toml: String,
+    config: Option<config::Config>,
+    provider: Option<Arc<dyn Provider>>,
+}
+
+#[cfg(test)]
+impl Default for AppStateBuilder {
+    fn default() -> Self {
+        Self {
+            endpoint: "http://127.0.0.1:1".to_string(),
+            toml: String::new(),
+            config: None,
+            provider: None,
+        }
+    }
+}
+
+#[cfg(test)]
+impl AppStateBuilder {
+    /// Send backend requests to `endpoint`
+    pub fn endpoint(mut self, endpoint: &str) -> Self {
+        self.endpoint = endpoint.to_string();
+        self
+    }
+
+    /// Add `toml` to the `[backend]` table
+    ///
+    /// After the backend keys it can open further tables, such as `[proxy]`.
+    pub fn toml(mut self, toml: &str) -> Self {
+        self.toml = toml.to_string();
+        self
+    }
+
+    /// Use `config` as is, instead of building one
+    pub fn config(mut self, config: config::Config) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    /// Use `provider` instead of the configured one
+    pub fn provider(mut self, provider: Arc<dyn Provider>) -> Self {
+        self.provider = Some(provider);
+        self
+    }
+
+    /// Create the state
+    pub fn build(self) -> AppState {
+        let config = self.config.unwrap_or_else(|| {
+            toml::from_str(&format!(
+                r#"
+                [backend]
+                endpoint = "{}"
+                {}
+
+                [backend.auth]
+                token = "secret"
+            "#,
+                self.endpoint, self.toml
+            ))
+            .unwrap()
+        });
+        let registry = Arc::new(ProviderRegistry::with_builtin());
+        let provider = self
+            .provider
+            .unwrap_or_else(|| registry.create(&config.backend.provider).unwrap());
+        let client = create_authenticated_client(&config, provider.as_ref()).unwrap();
+        AppState::new(config, client, provider, registry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::azure_openai::AzureOpenAiProvider;
+
+    #[test]
+    fn test_replace_swaps_snapshot() {
+        let state = AppState::builder().endpoint("http://old:9000").build();
+        let before = state.snapshot();
+
+        let new = AppState::builder()
+            .endpoint("http://new:9000")
+            .build()
+            .snapshot();
+        state.replace(
+            (*new.config).clone(),
+            new.client.clone(),
+            new.provider.clone(),
+        );
+
+        assert_eq!(before.config.backend.endpoint, "http://old:9000");
+        assert_eq!(state.snapshot().config.backend.endpoint, "http://new:9000");
+        assert!(Arc::ptr_eq(&before.readiness, &state.snapshot().readiness));
+    }
+
+    #[test]
+    fn test_clones_share_snapshot() {
+        let state = AppState::builder().endpoint("http://old:9000").build();
+        let clone = state.clone();
+
+        let new = AppState::builder()
+            .endpoint("http://new:9000")
+            .build()
+            .snapshot();
+        state.replace(
+            (*new.config).clone(),
+            new.client.clone(),
+            new.provider.clone(),
+        );
+
+        assert_eq!(clone.snapshot().config.backend.endpoint, "http://new:9000");
+    }
+
+    #[test]
+    fn test_builder_defaults_and_overrides() {
+        let snapshot = AppState::builder().build().snapshot();
+        assert_eq!(snapshot.config.backend.endpoint, "http://127.0.0.1:1");
+        assert_eq!(snapshot.provider.name(), "rhel_lightspeed");
+        assert!(snapshot.cache.is_none());
+
+        let snapshot = AppState::builder()
+            .endpoint("http://backend:9000")
+            .toml(
+                r#"
+                provider = "azure_openai"
+                deployment = "gpt-4o"
+                api_version = "2024-06-01"
+
+                [proxy.cache]
+                enabled = true
+            "#,
+            )
+            .build()
+            .snapshot();
+        assert_eq!(snapshot.config.backend.endpoint, "http://backend:9000");
+        assert_eq!(snapshot.provider.name(), "azure_openai");
+        assert!(snapshot.cache.is_some());
+
+        let snapshot = AppState::builder()
+            .provider(Arc::new(AzureOpenAiProvider))
+            .build()
+            .snapshot();
+        assert_eq!(snapshot.config.backend.provider, "rhel_lightspeed");
+        assert_eq!(snapshot.provider.name(), "azure_openai");
+    }
 }
@@ -0,0 +1,146 @@
+//! Prometheus metrics for the proxy
+//!
+//! Metrics are recorded through the `metrics` facade. When metrics are
+//! disabled no recorder is installed and recording is a no-op.
+
+use std::time::Duration;
+
+use axum::http::StatusCode;
+use metrics::{counter, gauge, histogram};
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+
+/// Total chat completion requests, labelled by mode and status
+const REQUESTS_TOTAL: &str = "clad_requests_total";
+/// Chat completion requests currently being processed
+const REQUESTS_IN_FLIGHT: &str = "clad_requests_in_flight";
+/// Latency of requests to the backend
+const BACKEND_LATENCY: &str = "clad_backend_request_duration_seconds";
+
+/// Response cache lookups, labelled by result
+const CACHE_LOOKUPS_TOTAL: &str = "clad_cache_lookups_total";
+
+/// Histogram buckets (in seconds) for backend latency
+const LATENCY_BUCKETS: &[f64] = &[0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0];
+
+/// Install the global Prometheus recorder and return a handle for rendering
+pub fn install_recorder() -> Result<PrometheusHandle, Box<dyn std::error::Error>> {
+    let handle = PrometheusBuilder::new()
+        .set_buckets_for_metric(Matcher::Full(BACKEND_LATENCY.to_string()), LATENCY_BUCKETS)?
+        .install_recorder()?;
+    Ok(handle)
+}
+
+/// Record a completed chat completion request
+pub fn record_request(streaming: bool, status: StatusCode) {
+    counter!(
+        REQUESTS_TOTAL,
+        "mode" => mode_label(streaming),
+        "status" => status.as_u16().to_string()
+    )
+    .increment(1);
+}
+
+/// Record how long a backend request took
+pub fn record_backend_latency(streaming: bool, elapsed: Duration) {
+    histogram!(BACKEND_LATENCY, "mode" => mode_label(streaming)).record(elapsed.as_secs_f64());
+}
+
+/// Record a response cache lookup
+pub fn record_cache_lookup(hit: bool) {
+    let result = if hit { "hit" } else { "miss" };
+    counter!(CACHE_LOOKUPS_TOTAL, "result" => result).increment(1);
+}
+
+fn mode_label(streaming: bool) -> &'static str {
+    if streaming {
+        "streaming"
+    } else {
+        "non_streaming"
+    }
+}
+
+/// Tracks a request as in-flight for as long as the guard is alive
+#[derive(Debug)]
+pub struct InFlightGuard;
+
+impl InFlightGuard {
+    /// Mark a request as in-flight
+    pub fn acquire() -> Self {
+        gauge!(REQUESTS_IN_FLIGHT).increment(1.0);
+        InFlightGuard
+    }
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        gauge!(REQUESTS_IN_FLIGHT).decrement(1.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render_with<F: FnOnce()>(f: F) -> String {
+        let recorder = PrometheusBuilder::new()
+            .set_buckets_for_metric(Matcher::Full(BACKEND_LATENCY.to_string()), LATENCY_BUCKETS)
+            .unwrap()
+            .build_recorder();
+        let handle = recorder.handle();
+        metrics::with_local_recorder(&recorder, || {
+            f();
+            handle.render()
+        })
+    }
+
+    #[test]
+    fn test_record_request_labels() {
+        let output = render_with(|| {
+            record_request(true, StatusCode::OK);
+            record_request(false, StatusCode::BAD_GATEWAY);
+            record_request(false, StatusCode::BAD_GATEWAY);
+        });
+
+        assert!(output.contains(r#"clad_requests_total{mode="streaming",status="200"} 1"#));
+        assert!(output.contains(r#"clad_requests_total{mode="non_streaming",status="502"} 2"#));
+    }
+
+    #[test]
+    fn test_record_backend_latency_histogram() {
+        let output = render_with(|| {
+            record_backend_latency(false, Duration::from_millis(300));
+        });
+
+        assert!(output.contains("clad_backend_request_duration_seconds_bucket"));
+        assert!(output.contains(r#"mode="non_streaming",le="0.5"} 1"#));
+    }
+
+    #[test]
+    fn test_record_cache_lookup() {
+        let output = render_with(|| {
+            record_cache_lookup(true);
+            record_cache_lookup(false);
+            record_cache_lookup(false);
+        });
+
+        assert!(output.contains(r#"clad_cache_lookups_total{result="hit"} 1"#));
+        assert!(output.contains(r#"clad_cache_lookups_total{result="miss"} 2"#));
+    }
+
+    #[test]
+    fn test_in_flight_guard() {
+        let recorder = PrometheusBuilder::new().build_recorder();
+        let handle = recorder.handle();
+
+        metrics::with_local_recorder(&recorder, || {
+            let first = InFlightGuard::acquire();
+            {
+                let _second = InFlightGuard::acquire();
+                assert!(handle.render().contains("clad_requests_in_flight 2"));
+            }
+            assert!(handle.render().contains("clad_requests_in_flight 1"));
+            drop(first);
+            assert!(handle.render().contains("clad_requests_in_flight 0"));
+        });
+    }
+}
@@ -0,0 +1,104 @@
+//! Helpers shared by the unit tests
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::http::{HeaderMap, StatusCode, Uri};
+use axum::routing::post;
+use axum::{Json, Router};
+use serde_json::{json, Value};
+
+use crate::state::AppState;
+
+/// Serve `router` on an ephemeral loopback port and return its base URL
+///
+/// The server runs until the test's runtime shuts down.
+pub async fn serve(router: Router) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let service = router.into_make_service_with_connect_info::<std::net::SocketAddr>();
+        axum::serve(listener, service).await.unwrap();
+    });
+    format!("http://{}", addr)
+}
+
+/// A request received by a [`MockBackend`]
+#[derive(Clone, Debug)]
+pub struct ReceivedRequest {
+    /// Request path and query
+    pub uri: Uri,
+    /// Request headers
+    pub headers: HeaderMap,
+    /// JSON request body
+    pub body: Value,
+}
+
+/// Fake backend that answers every request with the same response and
+/// records what it received
+#[derive(Clone, Debug)]
+pub struct MockBackend {
+    /// Base URL of the backend
+    pub url: String,
+    received: Arc<Mutex<Vec<ReceivedRequest>>>,
+}
+
+impl MockBackend {
+    /// Start a backend answering with `status` and `body`
+    pub async fn start(status: StatusCode, body: Value) -> Self {
+        Self::start_with_delay(status, body, Duration::ZERO).await
+    }
+
+    /// Start a backend answering 200 with a Red Hat Lightspeed reply of `text`
+    pub async fn replying(text: &str) -> Self {
+        Self::start(StatusCode::OK, json!({ "data": { "text": text } })).await
+    }
+
+    /// Start a backend that waits `delay` before answering
+    pub async fn start_with_delay(status: StatusCode, body: Value, delay: Duration) -> Self {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let recorder = received.clone();
+        let handler = post(
+            move |uri: Uri, headers: HeaderMap, Json(request): Json<Value>| {
+                let recorder = recorder.clone();
+                let body = body.clone();
+                async move {
+                    recorder.lock().unwrap().push(ReceivedRequest {
+                        uri,
+                        headers,
+                        body: request,
+                    });
+                    tokio::time::sleep(delay).await;
+                    (status, Json(body))
+                }
+            },
+        );
+        let router = Router::new()
+            .route("/", handler.clone())
+            .route("/*path", handler);
+
+        Self {
+            url: serve(router).await,
+            received,
+        }
+    }
+
+    /// Requests received so far
+    pub fn received(&self) -> Vec<ReceivedRequest> {
+        self.received.lock().unwrap().clone()
+    }
+
+    /// Number of requests received so far
+    pub fn hits(&self) -> usize {
+        self.received.lock().unwrap().len()
+    }
+
+    /// Application state pointing at this backend with bearer token
+    /// `secret`
+    ///
+    /// `extra` is added to the `[backend]` table, as by
+    /// [`AppStateBuilder::toml`](crate::state::AppStateBuilder::toml).
+    pub fn state(&self, extra: &str) -> AppState {
+        AppState::builder().endpoint(&self.url).toml(extra).build()
+    }
+}
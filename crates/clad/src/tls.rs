@@ -0,0 +1,132 @@
+//! HTTPS listener
+//!
+//! With `[proxy.tls]`, CLAD terminates TLS itself using rustls, so clients
+//! on other hosts can reach it without a reverse proxy. Without it the
+//! listener serves plain HTTP.
+
+use std::io;
+use std::net::SocketAddr;
+
+use axum::Router;
+use axum_server::tls_rustls::RustlsConfig;
+use tokio::net::TcpListener;
+
+use crate::config::TlsConfig;
+use crate::provider::warn_if_insecure_permissions;
+
+/// Load the certificate chain and private key named by `[proxy.tls]`
+///
+/// Both files must be PEM encoded. A key readable by group or others is
+/// accepted with a warning, as for backend credentials.
+pub async fn load(config: &TlsConfig) -> Result<RustlsConfig, String> {
+    warn_if_insecure_permissions(&config.key_file);
+
+    // Several providers may be compiled in, so rustls needs to be told
+    let _ = rustls::crypto::ring::default_provider().install_default();
+
+    RustlsConfig::from_pem_file(&config.cert_file, &config.key_file)
+        .await
+        .map_err(|e| {
+            format!(
+                "Invalid [proxy.tls] certificate {} or key {}: {}",
+                config.cert_file, config.key_file, e
+            )
+        })
+}
+
+/// Serve `app` on `listener`, over HTTPS when `tls` is set
+pub async fn serve(
+    listener: TcpListener,
+    app: Router,
+    tls: Option<RustlsConfig>,
+) -> io::Result<()> {
+    // Peer addresses are needed for per-IP rate limits
+    let service = app.into_make_service_with_connect_info::<SocketAddr>();
+    match tls {
+        Some(tls) => {
+            axum_server::from_tcp_rustls(listener.into_std()?, tls)
+                .serve(service)
+                .await
+        }
+        None => axum::serve(listener, service).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::get;
+    use std::fs;
+    use std::path::PathBuf;
+
+    /// Write a self-signed certificate for localhost and its key
+    fn self_signed(dir: &std::path::Path) -> TlsConfig {
+        let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let cert_file = dir.join("cert.pem");
+        let key_file = dir.join("key.pem");
+        fs::write(&cert_file, certified.cert.pem()).unwrap();
+        fs::write(&key_file, certified.key_pair.serialize_pem()).unwrap();
+
+        TlsConfig {
+            cert_file: cert_file.display().to_string(),
+            key_file: key_file.display().to_string(),
+        }
+    }
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("clad-test-tls-{}", uuid::Uuid::new_v4()));
+        fs::create_dir(&dir).unwrap();
+        dir
+    }
+
+    #[tokio::test]
+    async fn test_serves_https_when_configured() {
+        let dir = temp_dir();
+        let tls = load(&self_signed(&dir)).await.unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let app = Router::new().route("/health", get(|| async { "ok" }));
+        tokio::spawn(serve(listener, app, Some(tls)));
+
+        let client = reqwest::Client::builder()
+            .danger_accept_invalid_certs(true)
+            .build()
+            .unwrap();
+        let https = client
+            .get(format!("https://localhost:{}/health", port))
+            .send()
+            .await
+            .unwrap();
+        let plain = client
+            .get(format!("http://localhost:{}/health", port))
+            .send()
+            .await;
+        let _ = fs::remove_dir_all(&dir);
+
+        assert_eq!(https.status(), 200);
+        assert_eq!(https.text().await.unwrap(), "ok");
+        assert!(plain.map_or(true, |response| !response.status().is_success()));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_key_is_rejected() {
+        let dir = temp_dir();
+        let config = self_signed(&dir);
+        fs::write(&config.key_file, "not a key").unwrap();
+        let missing = TlsConfig {
+            cert_file: dir.join("missing.pem").display().to_string(),
+            ..config.clone()
+        };
+
+        let invalid = load(&config).await.unwrap_err();
+        let missing = load(&missing).await.unwrap_err();
+        let _ = fs::remove_dir_all(&dir);
+
+        assert!(
+            invalid.contains("Invalid [proxy.tls] certificate"),
+            "{}",
+            invalid
+        );
+        assert!(missing.contains("missing.pem"), "{}", missing);
+    }
+}
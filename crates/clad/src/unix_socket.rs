@@ -0,0 +1,131 @@
+//! Unix domain socket listener
+//!
+//! With `[proxy] listen = "unix:/path"`, CLAD accepts connections on a Unix
+//! socket instead of a TCP port, so single-host deployments expose no port at
+//! all. Access is then controlled by the socket file permissions.
+
+use std::fs;
+use std::io;
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::path::Path;
+use std::time::Duration;
+
+use axum::Router;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto::Builder;
+use hyper_util::service::TowerToHyperService;
+use tokio::net::UnixListener;
+use tracing::{debug, warn};
+
+/// Mode of the socket file: read and write for the owner and group
+const SOCKET_MODE: u32 = 0o660;
+
+/// Bind a Unix socket at `path`, replacing a stale socket left by a
+/// previous run
+///
+/// Anything at `path` other than a socket is left alone and reported as an
+/// error.
+pub fn bind(path: &Path) -> io::Result<UnixListener> {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => {
+            debug!("Removing stale socket {}", path.display());
+            fs::remove_file(path)?;
+        }
+        Ok(_) => {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} exists and is not a socket", path.display()),
+            ))
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+
+    let listener = UnixListener::bind(path)?;
+    fs::set_permissions(path, fs::Permissions::from_mode(SOCKET_MODE))?;
+    Ok(listener)
+}
+
+/// Serve `app` on `listener` until the process exits
+pub async fn serve(listener: UnixListener, app: Router) -> io::Result<()> {
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(e) => {
+                // Such as running out of file descriptors; retry shortly
+                warn!("Failed to accept connection: {}", e);
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                continue;
+            }
+        };
+
+        let service = TowerToHyperService::new(app.clone());
+        tokio::spawn(async move {
+            if let Err(e) = Builder::new(TokioExecutor::new())
+                .serve_connection_with_upgrades(TokioIo::new(stream), service)
+                .await
+            {
+                debug!("Connection error: {}", e);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::get;
+    use std::path::PathBuf;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::UnixStream;
+
+    fn socket_path() -> PathBuf {
+        std::env::temp_dir().join(format!("clad-test-{}.sock", uuid::Uuid::new_v4()))
+    }
+
+    #[tokio::test]
+    async fn test_serves_http_over_socket() {
+        let path = socket_path();
+        let listener = bind(&path).unwrap();
+        let app = Router::new().route("/health", get(|| async { "ok" }));
+        tokio::spawn(serve(listener, app));
+
+        let mut stream = UnixStream::connect(&path).await.unwrap();
+        stream
+            .write_all(b"GET /health HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        let _ = fs::remove_file(&path);
+
+        assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+        assert!(response.ends_with("ok"), "{}", response);
+        assert_eq!(mode & 0o777, SOCKET_MODE);
+    }
+
+    #[tokio::test]
+    async fn test_bind_replaces_stale_socket() {
+        let path = socket_path();
+        drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+
+        let result = bind(&path);
+        let _ = fs::remove_file(&path);
+
+        assert!(result.is_ok(), "{:?}", result.err());
+    }
+
+    #[tokio::test]
+    async fn test_bind_keeps_other_files() {
+        let path = socket_path();
+        fs::write(&path, "data").unwrap();
+
+        let err = bind(&path).unwrap_err();
+        let content = fs::read_to_string(&path).unwrap();
+        let _ = fs::remove_file(&path);
+
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(content, "data");
+    }
+}
@@ -80,13 +80,13 @@ pub fn extract_cli_structure(cmd: &clap::Command) -> CliCommand {
             let long = arg.get_long().unwrap_or(arg.get_id().as_str()).to_string();
             let short = arg.get_short().map(|c| c.to_string());
 
-            // Determine if this is a boolean flag
+            // Determine if this is a flag without a value (boolean or counter)
             let is_boolean = matches!(
                 arg.get_action(),
-                clap::ArgAction::SetTrue | clap::ArgAction::SetFalse
+                clap::ArgAction::SetTrue | clap::ArgAction::SetFalse | clap::ArgAction::Count
             );
 
-            // For boolean flags, we don't want a value name
+            // For flags without a value, we don't want a value name
             let value_name = if is_boolean {
                 None
             } else {
@@ -0,0 +1,120 @@
+//! Terminal color handling
+//!
+//! Color is used only when stdout is a terminal, `NO_COLOR` is unset (or
+//! empty) and `--no-color` was not given. All colored output goes through a
+//! [`Palette`] so every subcommand makes the same decision.
+
+use std::env;
+use std::ffi::OsString;
+use std::io::IsTerminal;
+use std::sync::OnceLock;
+
+/// Palette chosen at startup
+static PALETTE: OnceLock<Palette> = OnceLock::new();
+
+/// Styles applied by a [`Palette`]
+#[derive(Clone, Copy, Debug)]
+pub enum Style {
+    /// Bold text, for labels
+    Bold,
+    /// Green text, for things that are present or working
+    Green,
+    /// Red text, for things that are missing or failing
+    Red,
+    /// Cyan text, for code
+    Cyan,
+}
+
+impl Style {
+    fn code(self) -> &'static str {
+        match self {
+            Style::Bold => "1",
+            Style::Green => "32",
+            Style::Red => "31",
+            Style::Cyan => "36",
+        }
+    }
+}
+
+/// Applies ANSI styles when color is enabled
+#[derive(Clone, Copy, Debug)]
+pub struct Palette {
+    enabled: bool,
+}
+
+impl Palette {
+    /// Palette that never emits escape sequences
+    pub const PLAIN: Palette = Palette { enabled: false };
+
+    /// Decide whether to use color from the flag, `NO_COLOR` and whether
+    /// stdout `is_tty`
+    pub fn detect(no_color_flag: bool, is_tty: bool) -> Self {
+        if should_color(no_color_flag, env::var_os("NO_COLOR"), is_tty) {
+            Self { enabled: true }
+        } else {
+            Self::PLAIN
+        }
+    }
+
+    /// Wrap `text` in `style` when color is enabled
+    pub fn paint(&self, text: &str, style: Style) -> String {
+        if self.enabled {
+            format!("\x1b[{}m{}\x1b[0m", style.code(), text)
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+/// Choose the palette for this process from the `--no-color` flag
+pub fn init(no_color_flag: bool) {
+    let _ = PALETTE.set(Palette::detect(
+        no_color_flag,
+        std::io::stdout().is_terminal(),
+    ));
+}
+
+/// The palette chosen by [`init`], or detected from the environment
+pub fn palette() -> Palette {
+    *PALETTE.get_or_init(|| Palette::detect(false, std::io::stdout().is_terminal()))
+}
+
+fn should_color(no_color_flag: bool, no_color_env: Option<OsString>, is_tty: bool) -> bool {
+    let no_color_env = no_color_env.is_some_and(|value| !value.is_empty());
+    is_tty && !no_color_flag && !no_color_env
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_should_color() {
+        assert!(should_color(false, None, true));
+        assert!(should_color(false, Some(OsString::new()), true));
+        assert!(!should_color(true, None, true));
+        assert!(!should_color(false, Some(OsString::from("1")), true));
+        assert!(!should_color(false, None, false));
+    }
+
+    #[test]
+    #[allow(unsafe_code)]
+    fn test_no_color_env_disables_escape_sequences() {
+        unsafe {
+            // Detected as for a terminal, so only NO_COLOR can disable color
+            env::set_var("NO_COLOR", "1");
+            let palette = Palette::detect(false, true);
+            env::remove_var("NO_COLOR");
+
+            assert!(!palette.enabled);
+            assert_eq!(palette.paint("label", Style::Bold), "label");
+        }
+    }
+
+    #[test]
+    fn test_paint_when_enabled() {
+        let palette = Palette { enabled: true };
+
+        assert_eq!(palette.paint("ok", Style::Green), "\x1b[32mok\x1b[0m");
+    }
+}
@@ -3,18 +3,57 @@
 //! This module handles the chat functionality, including both interactive
 //! mode and quick query mode.
 
-use clap::Args;
-use log::{debug, error, info};
-use std::path::PathBuf;
-use std::process::{exit, Command};
+use clap::{Args, ValueEnum};
+use log::{debug, error, info, warn};
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::io::{self, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, SystemTime};
 
+use crate::color;
+use crate::config::CliConfig;
+use crate::context::system_context;
+use crate::error::CliError;
 use crate::helpers::{
-    ensure_goose_config_files, find_goose, get_filtered_env, is_goose_subcommand,
-    status_to_exit_code, validate_args, EX_CANTCREAT, EX_OSERR, EX_SOFTWARE, EX_UNAVAILABLE,
+    backend_address, backend_is_reachable, backend_uses_tls, check_output_path, editor_command,
+    ensure_goose_config_files, find_goose, get_filtered_env, goose_config_dir, is_goose_subcommand,
+    precheck_backend, read_attachment, read_from_editor, save_answer, status_to_exit_code,
+    validate_args, validate_goose_args, validate_session_name, Precheck, BACKEND_CHECK_TIMEOUT,
+    MAX_GOOSE_ARG_LENGTH,
 };
+use crate::markdown;
+use crate::output::advise;
+use crate::safety::{warning_banner, warning_reminder, DangerousPatterns};
+use crate::session::{
+    goose_sessions_dir, last_query_path, last_session_path, newest_goose_session, read_last_query,
+    read_last_session, record_last_query, record_last_session,
+};
+use crate::steps;
+
+/// Instruction prepended to the query by `--explain`
+pub const EXPLAIN_INSTRUCTION: &str = "Answer as a numbered list of short, actionable steps, \
+one step per line, with any command on its own line.";
+
+/// Maximum goose output kept when capturing it for `--json`
+pub const MAX_CAPTURED_OUTPUT: usize = 10 * 1024 * 1024; // 10MB
+
+/// Environment variable that turns the interactive banner off when set
+const NO_BANNER_ENV: &str = "NO_BANNER";
+
+/// A file attached to the query with `--attach`
+#[derive(Debug)]
+struct Attachment {
+    /// Path as given on the command line
+    name: String,
+    /// Text of the file
+    content: String,
+}
 
-/// Run the goose command with the given arguments
-pub fn run_goose(goose: &PathBuf, goose_args: &[String]) {
+/// Build the goose command with a filtered environment
+fn goose_command(goose: &PathBuf, goose_args: &[String]) -> Command {
     // Filter environment variables for security
     let filtered_env = get_filtered_env();
     debug!(
@@ -22,14 +61,20 @@ pub fn run_goose(goose: &PathBuf, goose_args: &[String]) {
         filtered_env.len()
     );
 
-    // Execute goose with proper I/O inheritance
     let mut cmd = Command::new(goose);
     cmd.args(goose_args)
         .env_clear() // Clear all env vars first
-        .envs(filtered_env) // Then set only filtered ones
-        .stdin(std::process::Stdio::inherit()) // Inherit stdin for interactive mode
-        .stdout(std::process::Stdio::inherit()) // Inherit stdout for output
-        .stderr(std::process::Stdio::inherit()); // Inherit stderr for errors
+        .envs(filtered_env); // Then set only filtered ones
+    cmd
+}
+
+/// Run the goose command with the given arguments, returning its exit code
+pub fn run_goose(goose: &PathBuf, goose_args: &[String]) -> Result<i32, CliError> {
+    // Execute goose with proper I/O inheritance
+    let mut cmd = goose_command(goose, goose_args);
+    cmd.stdin(Stdio::inherit()) // Inherit stdin for interactive mode
+        .stdout(Stdio::inherit()) // Inherit stdout for output
+        .stderr(Stdio::inherit()); // Inherit stderr for errors
 
     debug!("Spawning goose process");
 
@@ -46,24 +91,175 @@ pub fn run_goose(goose: &PathBuf, goose_args: &[String]) {
                 Ok(exit_status) => {
                     let exit_code = status_to_exit_code(exit_status);
                     info!("Goose process completed with exit code: {}", exit_code);
-                    exit(exit_code);
+                    Ok(exit_code)
                 }
                 Err(e) => {
                     error!("Failed to wait for goose process: {}", e);
-                    eprintln!("Error waiting for goose process: {}", e);
-                    exit(EX_OSERR);
+                    Err(CliError::GooseWait(e))
                 }
             }
         }
         Err(e) => {
             error!("Failed to execute goose: {}", e);
-            eprintln!("Error executing goose: {}", e);
-            eprintln!("Command: {:?}", goose);
-            exit(EX_SOFTWARE);
+            Err(CliError::GooseSpawn {
+                goose: goose.clone(),
+                source: e,
+            })
+        }
+    }
+}
+
+/// Output captured from a goose run
+#[derive(Debug)]
+pub struct CapturedOutput {
+    /// Everything goose wrote to stdout, up to the capture limit
+    pub stdout: String,
+    /// Whether output past the capture limit was dropped
+    pub truncated: bool,
+    /// Exit code of the goose process
+    pub exit_code: i32,
+}
+
+impl CapturedOutput {
+    /// Rewrite the answer as numbered steps, for `--explain`
+    ///
+    /// The output of a failed run is an error message, and left alone.
+    fn number_steps(&mut self) {
+        if self.exit_code == 0 {
+            self.stdout = steps::number_steps(&self.stdout);
         }
     }
 }
 
+/// Run goose and capture its stdout, keeping at most `limit` bytes
+///
+/// stderr is still inherited so goose errors reach the terminal.
+pub fn capture_goose(
+    goose: &PathBuf,
+    goose_args: &[String],
+    limit: usize,
+) -> io::Result<CapturedOutput> {
+    let mut cmd = goose_command(goose, goose_args);
+    cmd.stdin(Stdio::inherit())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit());
+
+    debug!("Spawning goose process with captured output");
+    let mut child = cmd.spawn()?;
+    let (stdout, truncated) = match child.stdout.take() {
+        Some(pipe) => read_bounded(pipe, limit)?,
+        None => (Vec::new(), false),
+    };
+    let exit_code = status_to_exit_code(child.wait()?);
+    info!("Goose process completed with exit code: {}", exit_code);
+
+    Ok(CapturedOutput {
+        stdout: String::from_utf8_lossy(&stdout).into_owned(),
+        truncated,
+        exit_code,
+    })
+}
+
+/// Read `reader` to the end, keeping at most `limit` bytes
+///
+/// Input past the limit is read and discarded so the writer never blocks on
+/// a full pipe. Returns the kept bytes and whether anything was dropped.
+fn read_bounded<R: Read>(mut reader: R, limit: usize) -> io::Result<(Vec<u8>, bool)> {
+    let mut kept = Vec::new();
+    let mut truncated = false;
+    let mut buf = [0u8; 8192];
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        let room = limit.saturating_sub(kept.len());
+        kept.extend_from_slice(&buf[..n.min(room)]);
+        truncated |= n > room;
+    }
+    Ok((kept, truncated))
+}
+
+/// Print captured output on stdout
+///
+/// A reader that went away, as with `c ... | head -1`, only means the output
+/// was cut short, so it is not an error.
+fn print_output(output: &str) {
+    if let Err(e) = write_output(&mut io::stdout().lock(), output) {
+        warn!("Failed to print the answer: {}", e);
+    }
+}
+
+/// Write `output` to `out` and flush it, ignoring a closed pipe
+fn write_output(out: &mut impl Write, output: &str) -> io::Result<()> {
+    match out.write_all(output.as_bytes()).and_then(|()| out.flush()) {
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => {
+            debug!("Stdout was closed before the answer was printed");
+            Ok(())
+        }
+        result => result,
+    }
+}
+
+/// Machine-readable result printed by `--json`
+fn json_result(query: &[String], output: &CapturedOutput) -> serde_json::Value {
+    serde_json::json!({
+        "query": query,
+        "response": output.stdout,
+        "exit_code": output.exit_code,
+        "truncated": output.truncated,
+    })
+}
+
+/// An attachment as appended to the query text
+fn format_attachment(attachment: &Attachment) -> String {
+    let newline = if attachment.content.ends_with('\n') {
+        ""
+    } else {
+        "\n"
+    };
+    format!(
+        "\n\n--- Attached file: {name} ---\n{}{}--- End of {name} ---",
+        attachment.content,
+        newline,
+        name = attachment.name
+    )
+}
+
+/// Whether to print the interactive banner, given the value of `NO_BANNER`
+///
+/// Like `NO_COLOR`, an empty value counts as unset.
+fn banner_enabled(no_banner: Option<OsString>) -> bool {
+    no_banner.is_none_or(|value| value.is_empty())
+}
+
+/// Parse `--timeout`, a positive number of seconds
+fn parse_timeout(value: &str) -> Result<Duration, String> {
+    value
+        .parse::<f64>()
+        .ok()
+        .filter(|seconds| *seconds > 0.0)
+        .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+        .ok_or_else(|| {
+            format!(
+                "invalid timeout {:?}, expected a positive number of seconds",
+                value
+            )
+        })
+}
+
+/// How the answer is printed, for `--format`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// As goose writes it
+    #[default]
+    Plain,
+    /// Rendered for the terminal when stdout is one
+    Markdown,
+}
+
 /// Start a chat session with the AI assistant
 #[derive(Args, Debug)]
 pub struct ChatArgs {
@@ -71,6 +267,83 @@ pub struct ChatArgs {
     #[arg(short, long, conflicts_with = "query")]
     pub interactive: bool,
 
+    /// Name of the interactive session to start or continue
+    #[arg(long, requires = "interactive", conflicts_with = "query")]
+    pub session: Option<String>,
+
+    /// Resume the previous interactive session
+    #[arg(long, requires = "interactive", conflicts_with = "query")]
+    pub resume: bool,
+
+    /// Warn if the assistant backend is not reachable before starting
+    #[arg(long)]
+    pub check_backend: bool,
+
+    /// Ask the backend whether it is rate limiting before starting
+    #[arg(long)]
+    pub precheck: bool,
+
+    /// Seconds to wait for the backend in --check-backend and --precheck
+    #[arg(long, value_name = "SECONDS", value_parser = parse_timeout)]
+    pub timeout: Option<Duration>,
+
+    /// Print the response as a single JSON object (query mode only)
+    #[arg(long, conflicts_with_all = ["interactive", "raw"])]
+    pub json: bool,
+
+    /// Answer as numbered, actionable steps, shown once complete (query mode only)
+    #[arg(long, conflicts_with_all = ["interactive", "raw"])]
+    pub explain: bool,
+
+    /// Add the OS and kernel versions to the query (query mode only)
+    #[arg(long, conflicts_with_all = ["interactive", "raw"])]
+    pub context: bool,
+
+    /// Don't add system facts, even when `context` is set in cli.toml
+    #[arg(long, conflicts_with = "context")]
+    pub no_context: bool,
+
+    /// Add the contents of a text file to the query, may be repeated (query mode only)
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["interactive", "raw"])]
+    pub attach: Vec<PathBuf>,
+
+    /// Write the query in $VISUAL or $EDITOR instead of on the command line
+    #[arg(long, conflicts_with_all = ["interactive", "raw", "query"])]
+    pub editor: bool,
+
+    /// Ask the previous query again, after editing it with --editor
+    #[arg(long, conflicts_with_all = ["interactive", "raw", "query"])]
+    pub repeat_last: bool,
+
+    /// Continue the most recent conversation (query mode only)
+    #[arg(long = "continue", conflicts_with_all = ["interactive", "raw"])]
+    pub continue_conversation: bool,
+
+    /// Warn when the answer suggests destructive commands such as `rm -rf` (query mode only)
+    #[arg(long, conflicts_with_all = ["interactive", "raw", "json"])]
+    pub safe: bool,
+
+    /// Render markdown in the answer when stdout is a terminal (query mode only)
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = OutputFormat::Plain,
+        conflicts_with_all = ["interactive", "raw", "json"]
+    )]
+    pub format: OutputFormat,
+
+    /// Save the answer to FILE instead of printing it (query mode only)
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["interactive", "raw", "json"])]
+    pub output: Option<PathBuf>,
+
+    /// Add the answer to the end of the --output file instead of replacing it
+    #[arg(long, requires = "output")]
+    pub append: bool,
+
+    /// Pass the arguments after `--` to goose verbatim (advanced)
+    #[arg(long, conflicts_with = "interactive")]
+    pub raw: bool,
+
     /// Your question or query
     #[arg(trailing_var_arg = true, allow_hyphen_values = false)]
     pub query: Vec<String>,
@@ -78,33 +351,105 @@ pub struct ChatArgs {
 
 impl ChatArgs {
     /// Execute the chat command - dispatches to appropriate mode
-    pub fn execute(&self) {
+    ///
+    /// Returns the exit code of goose.
+    pub fn execute(mut self) -> Result<i32, CliError> {
+        // Aliases and the context default only apply to quick queries
+        let mut safe_patterns = None;
+        let mut remember_query = true;
+        if !self.interactive && !self.raw {
+            match CliConfig::load() {
+                Ok(config) => {
+                    self.expand_alias(&config);
+                    self.context |= config.context && !self.no_context;
+                    remember_query = config.remember_last_query();
+                    safe_patterns = config.safe_patterns;
+                }
+                Err(e) => {
+                    warn!("Failed to load CLI config: {:#}", e);
+                    advise(format!("Warning: ignoring cli.toml: {:#}", e));
+                }
+            }
+        }
+
+        if self.repeat_last {
+            self.query = vec![Self::last_query(last_query_path().ok().as_deref())?];
+        }
+        if self.editor {
+            self.query = vec![Self::query_from_editor(&self.query.join(" "))?];
+        }
+
         // Early validation - check for invalid arguments before setup
         match (self.interactive, self.query.is_empty()) {
+            // Raw mode without goose arguments - error
+            (false, true) if self.raw => {
+                error!("Raw mode requires goose arguments");
+                return Err(CliError::Usage(
+                    "Please provide goose arguments after --raw --".to_string(),
+                ));
+            }
+
             // No arguments provided - error (should be handled by main CLI now)
             (false, true) => {
                 error!("Chat command requires either -i flag or a query");
-                eprintln!("Error: Please provide a query or use -i for interactive mode");
-                exit(EX_SOFTWARE);
+                return Err(CliError::Usage(
+                    "Please provide a query or use -i for interactive mode".to_string(),
+                ));
             }
 
-            // Query mode with restricted subcommand - show error
-            (false, false) if is_goose_subcommand(&self.query[0]) => {
+            // Query mode with restricted subcommand - show error. This runs
+            // after alias expansion, so aliases can't expand into one either.
+            (false, false) if !self.raw && is_goose_subcommand(&self.query[0]) => {
                 error!("Restricted goose subcommand: {}", self.query[0]);
-                eprintln!("Error: Direct goose subcommands are not supported");
-                exit(EX_SOFTWARE);
+                return Err(CliError::Usage(
+                    "Direct goose subcommands are not supported".to_string(),
+                ));
             }
 
             // Valid arguments - continue with setup
             _ => {}
         }
 
+        // --output after the query would be sent to the model as text
+        if let Some(option) = self.misplaced_output_option().filter(|_| !self.raw) {
+            error!("{} given after the query", option);
+            return Err(CliError::Usage(format!(
+                "{} must come before the query, e.g. c {} FILE \"question\"",
+                option, option
+            )));
+        }
+
+        let dangerous = if self.safe {
+            match DangerousPatterns::from_config(safe_patterns.as_deref()) {
+                Ok(patterns) => Some(patterns),
+                Err(e) => {
+                    error!("Invalid safe_patterns: {:#}", e);
+                    return Err(CliError::SafePatterns(e));
+                }
+            }
+        } else {
+            None
+        };
+
+        if let Some(name) = &self.session {
+            if let Err(e) = validate_session_name(name) {
+                error!("Invalid session name: {}", e);
+                return Err(CliError::Usage(e.to_string()));
+            }
+        }
+
         // Ensure config files exist before running goose
         if let Err(e) = ensure_goose_config_files() {
             error!("Failed to ensure config files: {:#}", e);
-            eprintln!("Error setting up configuration: {}", e);
-            eprintln!("This may be due to insufficient permissions or disk space.");
-            exit(EX_CANTCREAT);
+            return Err(CliError::ConfigSetup(e));
+        }
+
+        let timeout = self.timeout.unwrap_or(BACKEND_CHECK_TIMEOUT);
+        if self.check_backend {
+            Self::warn_if_backend_down(timeout);
+        }
+        if self.precheck {
+            Self::fail_if_rate_limited(timeout)?;
         }
 
         // Find the goose binary
@@ -112,67 +457,517 @@ impl ChatArgs {
             Ok(path) => path,
             Err(e) => {
                 error!("Failed to find goose binary: {:#}", e);
-                eprintln!("Error: goose binary not found");
-                eprintln!("Please ensure goose is installed at /usr/bin/goose");
-                eprintln!("Or set GOOSE_BINARY environment variable to the correct path");
-                exit(EX_UNAVAILABLE);
+                return Err(CliError::GooseNotFound);
             }
         };
 
         info!("Using goose binary: {:?}", goose);
 
         // Dispatch to appropriate mode
+        let started = SystemTime::now();
         match (self.interactive, self.query.is_empty()) {
             // Interactive mode
-            (true, _) => self.execute_interactive(&goose),
+            (true, _) => {
+                let status = self.execute_interactive(&goose);
+                self.record_session(started);
+                status
+            }
+
+            // Raw passthrough mode
+            (false, false) if self.raw => self.execute_raw(&goose),
 
             // Query mode (already validated above)
-            (false, false) => self.execute_query(&goose),
+            (false, false) => {
+                let status = self.execute_query(&goose, dangerous.as_ref());
+                self.record_session(started);
+                let status = status?;
+                if status == 0 && remember_query {
+                    Self::record_query(&self.query.join(" "));
+                }
+                Ok(status)
+            }
 
             // This should never happen due to early validation above
             (false, true) => unreachable!("Empty query should have been handled earlier"),
         }
     }
 
+    /// The most recent query recorded at `path`, for `--repeat-last`
+    fn last_query(path: Option<&Path>) -> Result<String, CliError> {
+        let Some(query) = path.and_then(read_last_query) else {
+            error!("No previous query to repeat");
+            return Err(CliError::NoLastQuery);
+        };
+        info!("Repeating the last query");
+        Ok(query)
+    }
+
+    /// Read the query from the user's editor, starting from `initial`
+    ///
+    /// Like `git commit`, an editor exiting unsuccessfully or an empty file
+    /// aborts.
+    fn query_from_editor(initial: &str) -> Result<String, CliError> {
+        let editor = editor_command();
+        let query = read_from_editor(&editor, initial).map_err(|e| {
+            error!("Failed to read the query from the editor: {:#}", e);
+            CliError::Editor(e)
+        })?;
+
+        let query = query.trim();
+        if query.is_empty() {
+            error!("Query from the editor is empty");
+            return Err(CliError::EmptyQuery);
+        }
+        debug!("Read a {} byte query from the editor", query.len());
+        Ok(query.to_string())
+    }
+
+    /// `--output` or `--append` found among the query words
+    ///
+    /// The query takes every argument after its first word, so these
+    /// options given after it would end up in the question.
+    fn misplaced_output_option(&self) -> Option<&'static str> {
+        self.query.iter().find_map(|word| {
+            ["--output", "--append"]
+                .into_iter()
+                .find(|option| word == option || word.starts_with(&format!("{}=", option)))
+        })
+    }
+
+    /// Replace a single-word query naming an alias with its expansion
+    ///
+    /// Goose subcommands are never expanded, so they are still rejected
+    /// like any other query starting with one.
+    fn expand_alias(&mut self, config: &CliConfig) {
+        if self
+            .query
+            .first()
+            .is_some_and(|word| is_goose_subcommand(word))
+        {
+            return;
+        }
+        if let Some(expansion) = config.expand_alias(&self.query) {
+            info!("Expanding alias {:?} to {:?}", self.query[0], expansion);
+            self.query = vec![expansion.to_string()];
+        }
+    }
+
+    /// goose's config.yaml
+    fn goose_config_yaml() -> Option<String> {
+        goose_config_dir()
+            .ok()
+            .and_then(|dir| fs::read_to_string(dir.join("config.yaml")).ok())
+    }
+
+    /// Warn when nothing is listening on the backend configured in config.yaml
+    fn warn_if_backend_down(timeout: Duration) {
+        let Some(address) = Self::goose_config_yaml().and_then(|yaml| backend_address(&yaml))
+        else {
+            debug!("No backend address configured, skipping backend check");
+            return;
+        };
+
+        if !backend_is_reachable(&address, timeout) {
+            warn!("Backend not reachable at {}", address);
+            advise(format!(
+                "Warning: the assistant backend doesn't seem to be running on {} - is clad started?",
+                address
+            ));
+        }
+    }
+
+    /// Fail when the backend configured in config.yaml answers 429
+    ///
+    /// An unreachable backend is left for goose to report.
+    fn fail_if_rate_limited(timeout: Duration) -> Result<(), CliError> {
+        let config_yaml = Self::goose_config_yaml().unwrap_or_default();
+        let Some(address) = backend_address(&config_yaml) else {
+            debug!("No backend address configured, skipping rate limit pre-check");
+            return Ok(());
+        };
+
+        match precheck_backend(&address, backend_uses_tls(&config_yaml), timeout) {
+            Precheck::RateLimited(retry_after) => {
+                warn!("Backend at {} is rate limiting requests", address);
+                return Err(CliError::RateLimited(retry_after));
+            }
+            Precheck::Ok => debug!("Backend at {} is not rate limiting", address),
+            Precheck::Unreachable => debug!("Rate limit pre-check got no answer from {}", address),
+        }
+        Ok(())
+    }
+
     /// Execute interactive session mode
-    fn execute_interactive(&self, goose: &PathBuf) {
+    fn execute_interactive(&self, goose: &PathBuf) -> Result<i32, CliError> {
         debug!("Interactive mode requested");
-        let goose_args = Self::build_interactive_args();
+        // Without a name, --resume leaves goose to pick the session it resumes
+        let goose_args = Self::build_interactive_args(self.session.as_deref(), self.resume);
         debug!("Goose arguments: {:?}", goose_args);
 
+        if banner_enabled(env::var_os(NO_BANNER_ENV)) {
+            Self::print_banner();
+        }
+
         // Execute goose in interactive mode
-        run_goose(&goose, &goose_args);
+        run_goose(&goose, &goose_args)
+    }
+
+    /// Print the interactive banner from cli.toml on stderr
+    ///
+    /// Like other advisory messages it is dropped with `--quiet`, and it never
+    /// reaches stdout.
+    fn print_banner() {
+        match CliConfig::load().and_then(|config| config.banner()) {
+            Ok(Some(banner)) => advise(banner),
+            Ok(None) => debug!("Banner disabled in cli.toml"),
+            Err(e) => {
+                warn!("Failed to load the banner: {:#}", e);
+                advise(format!("Warning: no banner: {:#}", e));
+            }
+        }
     }
 
     /// Execute query mode
-    fn execute_query(&self, goose: &PathBuf) {
+    ///
+    /// With `dangerous`, the answer is checked for destructive commands
+    /// before it is shown.
+    fn execute_query(
+        &self,
+        goose: &PathBuf,
+        dangerous: Option<&DangerousPatterns>,
+    ) -> Result<i32, CliError> {
         // Validate arguments
         if let Err(e) = validate_args(&self.query) {
             error!("Invalid arguments: {}", e);
-            eprintln!("Error: {}", e);
-            exit(EX_SOFTWARE);
+            return Err(CliError::Usage(e.to_string()));
         }
 
         debug!("Query mode with {} arguments", self.query.len());
+        if self.repeat_last && !self.editor {
+            advise(format!("Asking again: {}", self.query.join(" ")));
+        }
 
-        let goose_args = Self::build_query_args(&self.query);
+        let context = if self.context { system_context() } else { None };
+        let session = self.continued_session(last_session_path().ok().as_deref());
+        let goose_args = self.checked_goose_args(context, session.as_deref())?;
         debug!("Goose arguments: {:?}", goose_args);
 
+        if self.json {
+            return self.execute_json(goose, &goose_args);
+        }
+        if let Some(path) = &self.output {
+            if let Err(e) = check_output_path(path) {
+                error!("Invalid output file {:?}: {:#}", path, e);
+                return Err(CliError::Output(e));
+            }
+        }
+        let markdown = self.format == OutputFormat::Markdown && io::stdout().is_terminal();
+        if self.format == OutputFormat::Markdown && !markdown {
+            debug!("stdout is not a terminal, printing the answer as plain text");
+        }
+        if dangerous.is_some() || markdown || self.explain || self.output.is_some() {
+            let save = self.output.as_deref().map(|path| (path, self.append));
+            return Self::execute_captured(
+                goose,
+                &goose_args,
+                dangerous,
+                markdown,
+                self.explain,
+                save,
+            );
+        }
+
         // Execute goose with query
-        run_goose(&goose, &goose_args);
+        run_goose(&goose, &goose_args)
+    }
+
+    /// Goose arguments for the query, with the system `context` and the
+    /// `--attach` files read in, checked against the argument limits
+    ///
+    /// The files are added to the last query argument, so together with it
+    /// they must fit in [`MAX_GOOSE_ARG_LENGTH`].
+    fn checked_goose_args(
+        &self,
+        context: Option<&str>,
+        session: Option<&str>,
+    ) -> Result<Vec<String>, CliError> {
+        let last_length = self.query.last().map_or(0, String::len);
+        let attachments = Self::read_attachments(
+            &self.attach,
+            MAX_GOOSE_ARG_LENGTH.saturating_sub(last_length),
+        )?;
+
+        let goose_args = self.query_goose_args(context, session, &attachments);
+        if let Err(e) = validate_goose_args(&goose_args) {
+            error!("Invalid goose arguments: {}", e);
+            return Err(CliError::Usage(e.to_string()));
+        }
+        Ok(goose_args)
+    }
+
+    /// Goose arguments for the query, with the system `context` and the
+    /// `--attach` files
+    ///
+    /// With a `session` to continue, goose resumes it; otherwise goose starts
+    /// a session as it always has.
+    fn query_goose_args(
+        &self,
+        context: Option<&str>,
+        session: Option<&str>,
+        attachments: &[Attachment],
+    ) -> Vec<String> {
+        let mut goose_args =
+            Self::build_query_args(&self.query, self.explain, context, attachments);
+        if let Some(name) = session {
+            goose_args.splice(1..1, Self::session_args(Some(name), true));
+        }
+        goose_args
+    }
+
+    /// Session `--continue` resumes, as recorded at `path`
+    ///
+    /// Without a recorded session, a warning is printed and the query starts
+    /// a new one.
+    fn continued_session(&self, path: Option<&Path>) -> Option<String> {
+        if !self.continue_conversation {
+            return None;
+        }
+        match path.and_then(read_last_session) {
+            Some(name) => {
+                info!("Continuing session {:?}", name);
+                Some(name)
+            }
+            None => {
+                warn!("No previous session to continue");
+                advise("Warning: no previous conversation to continue, starting a new one");
+                None
+            }
+        }
+    }
+
+    /// Remember the session goose ran in for `--continue`
+    ///
+    /// This is the `--session` name when one was given, and otherwise the
+    /// session goose wrote to since `started`. Failing to record it only
+    /// loses the ability to continue it, so it is only logged.
+    fn record_session(&self, started: SystemTime) {
+        let name = match &self.session {
+            Some(name) => Some(name.clone()),
+            None => goose_sessions_dir()
+                .ok()
+                .and_then(|dir| newest_goose_session(&dir, started)),
+        };
+        let Some(name) = name else {
+            debug!("No goose session to record");
+            return;
+        };
+        if let Err(e) = last_session_path().and_then(|path| record_last_session(&path, &name)) {
+            warn!("Failed to record session {:?}: {:#}", name, e);
+        }
+    }
+
+    /// Remember `query`, answered successfully, for `--repeat-last`
+    ///
+    /// Failing to do so only loses the ability to repeat it, so it is only
+    /// logged.
+    fn record_query(query: &str) {
+        if let Err(e) = last_query_path().and_then(|path| record_last_query(&path, query)) {
+            warn!("Failed to record the query: {:#}", e);
+        }
+    }
+
+    /// Read the `--attach` files in order, failing if one can't be attached
+    ///
+    /// Together the files may hold at most `limit` bytes.
+    fn read_attachments(paths: &[PathBuf], mut limit: usize) -> Result<Vec<Attachment>, CliError> {
+        let mut attachments = Vec::with_capacity(paths.len());
+        for path in paths {
+            if !path.exists() {
+                error!("Attachment not found: {:?}", path);
+                return Err(CliError::MissingAttachment(path.clone()));
+            }
+
+            match read_attachment(path, limit) {
+                Ok(content) => {
+                    debug!("Attaching {:?}: {} bytes", path, content.len());
+                    limit -= content.len();
+                    attachments.push(Attachment {
+                        name: path.display().to_string(),
+                        content,
+                    });
+                }
+                Err(e) => {
+                    error!("Failed to attach {:?}: {:#}", path, e);
+                    return Err(CliError::Attachment(e));
+                }
+            }
+        }
+        Ok(attachments)
+    }
+
+    /// Run the query with captured output and print it as JSON
+    ///
+    /// With `--explain`, the response is rewritten as numbered steps.
+    fn execute_json(&self, goose: &PathBuf, goose_args: &[String]) -> Result<i32, CliError> {
+        match capture_goose(goose, goose_args, MAX_CAPTURED_OUTPUT) {
+            Ok(mut output) => {
+                if output.truncated {
+                    warn!("Goose output exceeded {} bytes", MAX_CAPTURED_OUTPUT);
+                }
+                if self.explain {
+                    output.number_steps();
+                }
+                print_output(&format!("{}\n", json_result(&self.query, &output)));
+                Ok(output.exit_code)
+            }
+            Err(e) => {
+                error!("Failed to execute goose: {}", e);
+                Err(CliError::GooseSpawn {
+                    goose: goose.clone(),
+                    source: e,
+                })
+            }
+        }
+    }
+
+    /// Run goose with its answer captured, printing a warning before and
+    /// after it when a line matches `dangerous`, and rendering its markdown
+    /// with `markdown`
+    ///
+    /// The answer is only shown once goose has finished, rewritten as
+    /// numbered steps with `explain`. With `save`, a
+    /// successful answer is written to the file instead, appended to it when
+    /// the flag is set; a failed one is still printed.
+    fn execute_captured(
+        goose: &PathBuf,
+        goose_args: &[String],
+        dangerous: Option<&DangerousPatterns>,
+        markdown: bool,
+        explain: bool,
+        save: Option<(&Path, bool)>,
+    ) -> Result<i32, CliError> {
+        let mut output = match capture_goose(goose, goose_args, MAX_CAPTURED_OUTPUT) {
+            Ok(output) => output,
+            Err(e) => {
+                error!("Failed to execute goose: {}", e);
+                return Err(CliError::GooseSpawn {
+                    goose: goose.clone(),
+                    source: e,
+                });
+            }
+        };
+        if output.truncated {
+            warn!("Goose output exceeded {} bytes", MAX_CAPTURED_OUTPUT);
+        }
+        if explain {
+            output.number_steps();
+        }
+
+        let flagged = dangerous.map_or_else(Vec::new, |dangerous| dangerous.find(&output.stdout));
+        let palette = color::palette();
+        if !flagged.is_empty() {
+            warn!(
+                "Answer contains {} potentially destructive lines",
+                flagged.len()
+            );
+            eprintln!("{}\n", warning_banner(&flagged, palette));
+        }
+        match save {
+            Some((path, append)) if output.exit_code == 0 => {
+                if let Err(e) = save_answer(path, &output.stdout, append) {
+                    error!("Failed to save the answer: {:#}", e);
+                    return Err(CliError::Output(e));
+                }
+                info!("Saved {} bytes to {:?}", output.stdout.len(), path);
+                advise(format!("Saved the answer to {}", path.display()));
+            }
+            _ => {
+                if save.is_some() {
+                    warn!("Goose failed, not saving its output");
+                }
+                if markdown {
+                    print_output(&markdown::render(&output.stdout, palette));
+                } else {
+                    print_output(&output.stdout);
+                }
+            }
+        }
+        if output.truncated {
+            advise(format!(
+                "Warning: the answer was cut at {} bytes",
+                MAX_CAPTURED_OUTPUT
+            ));
+        }
+        if !flagged.is_empty() {
+            eprintln!("\n{}", warning_reminder(palette));
+        }
+        Ok(output.exit_code)
+    }
+
+    /// Execute raw passthrough mode
+    fn execute_raw(&self, goose: &PathBuf) -> Result<i32, CliError> {
+        // Validate arguments
+        if let Err(e) = validate_args(&self.query) {
+            error!("Invalid arguments: {}", e);
+            return Err(CliError::Usage(e.to_string()));
+        }
+
+        info!("Raw mode with {} arguments", self.query.len());
+        debug!("Goose arguments: {:?}", self.query);
+
+        // Execute goose with the arguments as given
+        run_goose(goose, &self.query)
     }
 
     /// Build arguments for interactive mode
-    fn build_interactive_args() -> Vec<String> {
-        vec!["session".to_string()]
+    fn build_interactive_args(session: Option<&str>, resume: bool) -> Vec<String> {
+        let mut goose_args = vec!["session".to_string()];
+        goose_args.extend(Self::session_args(session, resume));
+        goose_args
+    }
+
+    /// Arguments naming the goose session and whether to resume it
+    ///
+    /// Both `goose session` and `goose run` take them.
+    fn session_args(session: Option<&str>, resume: bool) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(name) = session {
+            args.extend(["--name".to_string(), name.to_string()]);
+        }
+        if resume {
+            args.push("--resume".to_string());
+        }
+        args
     }
 
     /// Build arguments for query mode
-    fn build_query_args(query: &[String]) -> Vec<String> {
+    ///
+    /// The system `context` and, with `explain`, [`EXPLAIN_INSTRUCTION`] are
+    /// prepended to the text of the first query argument, so they still
+    /// reach goose through `-t`. `attachments` are appended to the last one,
+    /// each between lines naming the file.
+    fn build_query_args(
+        query: &[String],
+        explain: bool,
+        context: Option<&str>,
+        attachments: &[Attachment],
+    ) -> Vec<String> {
         let mut goose_args = vec!["run".to_string(), "-t".to_string()];
         // SECURITY: Don't join arguments - pass them separately
         // The goose binary will handle them appropriately
         goose_args.extend_from_slice(query);
+        let preamble: Vec<&str> = context
+            .into_iter()
+            .chain(explain.then_some(EXPLAIN_INSTRUCTION))
+            .collect();
+        if let (false, Some(text)) = (preamble.is_empty(), goose_args.get_mut(2)) {
+            *text = format!("{}\n\n{}", preamble.join("\n\n"), text);
+        }
+        if let Some(text) = goose_args[2..].last_mut() {
+            for attachment in attachments {
+                text.push_str(&format_attachment(attachment));
+            }
+        }
         goose_args
     }
 }
@@ -180,6 +975,7 @@ impl ChatArgs {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::helpers::{EX_DATAERR, EX_NOINPUT, EX_SOFTWARE};
 
     // ============================================================================
     // Tests for ChatArgs Parsing
@@ -193,14 +989,82 @@ mod tests {
 
     #[test]
     fn test_build_interactive_args() {
-        let args = ChatArgs::build_interactive_args();
+        let args = ChatArgs::build_interactive_args(None, false);
         assert_eq!(args, vec!["session"]);
     }
 
+    #[test]
+    fn test_build_interactive_args_with_session_name() {
+        let args = ChatArgs::build_interactive_args(Some("foo"), false);
+        assert_eq!(args, vec!["session", "--name", "foo"]);
+    }
+
+    #[test]
+    fn test_build_interactive_args_with_resume() {
+        let args = ChatArgs::build_interactive_args(None, true);
+        assert_eq!(args, vec!["session", "--resume"]);
+
+        let args = ChatArgs::build_interactive_args(Some("foo"), true);
+        assert_eq!(args, vec!["session", "--name", "foo", "--resume"]);
+    }
+
+    #[test]
+    fn test_plain_runs_keep_their_goose_args() {
+        // No session is named unless asked for
+        let mut interactive = query_args(&[]);
+        interactive.interactive = true;
+        assert_eq!(
+            ChatArgs::build_interactive_args(interactive.session.as_deref(), interactive.resume),
+            vec!["session"]
+        );
+
+        assert_eq!(
+            query_args(&["how do I list files"]).query_goose_args(None, None, &[]),
+            ChatArgs::build_query_args(&["how do I list files".to_string()], false, None, &[])
+        );
+    }
+
+    #[test]
+    fn test_continue_resumes_the_recorded_session() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("last_session");
+        record_last_session(&path, "20250101_3").unwrap();
+        let mut chat = query_args(&["and now?"]);
+        chat.continue_conversation = true;
+
+        let session = chat.continued_session(Some(&path));
+        assert_eq!(session.as_deref(), Some("20250101_3"));
+
+        let args = chat.query_goose_args(None, session.as_deref(), &[]);
+        assert_eq!(args[..4], ["run", "--name", "20250101_3", "--resume"]);
+        assert_eq!(
+            args[4..],
+            ChatArgs::build_query_args(&["and now?".to_string()], false, None, &[])[1..]
+        );
+        assert!(ChatArgs::session_args(None, false).is_empty());
+
+        // Without --continue, the recorded session is left alone
+        chat.continue_conversation = false;
+        assert_eq!(chat.continued_session(Some(&path)), None);
+    }
+
+    #[test]
+    fn test_continue_without_recorded_session_starts_fresh() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let mut chat = query_args(&["and now?"]);
+        chat.continue_conversation = true;
+
+        assert_eq!(
+            chat.continued_session(Some(&temp_dir.path().join("last_session"))),
+            None
+        );
+        assert_eq!(chat.continued_session(None), None);
+    }
+
     #[test]
     fn test_build_query_args_single_word() {
         let query = vec!["hello".to_string()];
-        let args = ChatArgs::build_query_args(&query);
+        let args = ChatArgs::build_query_args(&query, false, None, &[]);
 
         assert_eq!(args.len(), 3);
         assert_eq!(args[0], "run");
@@ -217,7 +1081,7 @@ mod tests {
             "list".to_string(),
             "files".to_string(),
         ];
-        let args = ChatArgs::build_query_args(&query);
+        let args = ChatArgs::build_query_args(&query, false, None, &[]);
 
         assert_eq!(args.len(), 7);
         assert_eq!(args[0], "run");
@@ -232,7 +1096,7 @@ mod tests {
     #[test]
     fn test_build_query_args_with_spaces() {
         let query = vec!["query with spaces".to_string()];
-        let args = ChatArgs::build_query_args(&query);
+        let args = ChatArgs::build_query_args(&query, false, None, &[]);
 
         assert_eq!(args.len(), 3);
         assert_eq!(args[0], "run");
@@ -247,7 +1111,7 @@ mod tests {
             "this!".to_string(),
             "meaning?".to_string(),
         ];
-        let args = ChatArgs::build_query_args(&query);
+        let args = ChatArgs::build_query_args(&query, false, None, &[]);
 
         assert_eq!(args.len(), 5);
         assert_eq!(args[0], "run");
@@ -261,7 +1125,7 @@ mod tests {
     fn test_build_query_args_preserves_boundaries() {
         // Critical security test: ensure arguments are passed separately
         let query = vec!["arg1".to_string(), "arg2".to_string(), "arg3".to_string()];
-        let args = ChatArgs::build_query_args(&query);
+        let args = ChatArgs::build_query_args(&query, false, None, &[]);
 
         assert_eq!(args.len(), 5);
         assert_eq!(args[0], "run");
@@ -279,10 +1143,179 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_build_query_args_with_explain() {
+        let query = vec!["restart".to_string(), "httpd".to_string()];
+        let args = ChatArgs::build_query_args(&query, true, None, &[]);
+
+        assert_eq!(args.len(), 4);
+        assert_eq!(args[..2], ["run", "-t"]);
+        assert_eq!(args[2], format!("{}\n\nrestart", EXPLAIN_INSTRUCTION));
+        assert_eq!(args[3], "httpd");
+        assert!(!is_goose_subcommand(&args[2]));
+    }
+
+    #[test]
+    fn test_build_query_args_with_context() {
+        let query = vec!["restart".to_string(), "httpd".to_string()];
+        let context = "Context: my system runs Fedora 40.";
+
+        let args = ChatArgs::build_query_args(&query, false, Some(context), &[]);
+        assert_eq!(args[2], format!("{}\n\nrestart", context));
+        assert_eq!(args[3], "httpd");
+
+        let args = ChatArgs::build_query_args(&query, true, Some(context), &[]);
+        assert_eq!(
+            args[2],
+            format!("{}\n\n{}\n\nrestart", context, EXPLAIN_INSTRUCTION)
+        );
+    }
+
+    #[test]
+    fn test_build_query_args_with_attachments() {
+        let query = vec!["what's".to_string(), "wrong here".to_string()];
+        let attachments = [
+            Attachment {
+                name: "main.rs".to_string(),
+                content: "fn main() {}\n".to_string(),
+            },
+            Attachment {
+                name: "lib.rs".to_string(),
+                content: "pub fn f() {}".to_string(),
+            },
+        ];
+
+        let args = ChatArgs::build_query_args(&query, false, None, &attachments);
+
+        assert_eq!(args.len(), 4);
+        assert_eq!(args[2], "what's");
+        assert_eq!(
+            args[3],
+            "wrong here\n\n\
+             --- Attached file: main.rs ---\nfn main() {}\n--- End of main.rs ---\n\n\
+             --- Attached file: lib.rs ---\npub fn f() {}\n--- End of lib.rs ---"
+        );
+        assert_eq!(
+            ChatArgs::build_query_args(&[], false, None, &attachments),
+            ["run", "-t"]
+        );
+    }
+
+    #[test]
+    fn test_read_attachments_in_order() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let first = temp_dir.path().join("first.txt");
+        let second = temp_dir.path().join("second.txt");
+        fs::write(&first, "one").unwrap();
+        fs::write(&second, "two").unwrap();
+
+        let attachments = ChatArgs::read_attachments(&[second.clone(), first.clone()], 6).unwrap();
+
+        let names: Vec<&str> = attachments.iter().map(|a| a.name.as_str()).collect();
+        let contents: Vec<&str> = attachments.iter().map(|a| a.content.as_str()).collect();
+        assert_eq!(names, [second.to_str().unwrap(), first.to_str().unwrap()]);
+        assert_eq!(contents, ["two", "one"]);
+    }
+
+    #[test]
+    fn test_read_attachments_errors() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let file = temp_dir.path().join("big.txt");
+        fs::write(&file, "too large").unwrap();
+
+        let err = ChatArgs::read_attachments(&[temp_dir.path().join("missing")], 100).unwrap_err();
+        assert_eq!(err.exit_code(), EX_NOINPUT);
+        let err = ChatArgs::read_attachments(&[file], 4).unwrap_err();
+        assert_eq!(err.exit_code(), EX_DATAERR);
+    }
+
+    #[test]
+    fn test_attachments_fit_in_one_argument() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let big = temp_dir.path().join("big.log");
+        fs::write(&big, "a".repeat(130 * 1024)).unwrap();
+        let mut chat = query_args(&["what", "failed?"]);
+        chat.attach = vec![big];
+
+        let err = chat.checked_goose_args(None, None).unwrap_err();
+        assert_eq!(err.exit_code(), EX_DATAERR);
+
+        // The file fits, but not with the lines naming it and the context
+        let fits = temp_dir.path().join("fits.log");
+        fs::write(&fits, "a".repeat(MAX_GOOSE_ARG_LENGTH - "failed?".len())).unwrap();
+        chat.attach = vec![fits];
+
+        let err = chat
+            .checked_goose_args(Some("Context: Fedora"), None)
+            .unwrap_err();
+        assert_eq!(err.exit_code(), EX_SOFTWARE);
+
+        chat.attach = vec![];
+        let args = chat.checked_goose_args(None, None).unwrap();
+        assert_eq!(args, ["run", "-t", "what", "failed?"]);
+    }
+
+    // ============================================================================
+    // Tests for exit codes
+    // ============================================================================
+
+    #[test]
+    fn test_execute_rejects_unusable_arguments() {
+        let mut raw = query_args(&[]);
+        raw.raw = true;
+        let mut bad_session = query_args(&[]);
+        bad_session.interactive = true;
+        bad_session.session = Some("../escape".to_string());
+
+        for (chat, expected) in [
+            (query_args(&[]), "Please provide a query"),
+            (raw, "after --raw --"),
+            (query_args(&["session"]), "Direct goose subcommands"),
+            (
+                query_args(&["generate a unit", "--output", "nginx.service"]),
+                "--output must come before the query",
+            ),
+            (bad_session, "path separators"),
+        ] {
+            let err = chat.execute().unwrap_err();
+            assert_eq!(err.exit_code(), EX_SOFTWARE);
+            assert!(err.to_string().contains(expected), "{}", err);
+        }
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_run_goose_returns_exit_code() {
+        let shell = PathBuf::from("/bin/sh");
+        let args = ["-c".to_string(), "exit 3".to_string()];
+
+        assert_eq!(run_goose(&shell, &args).unwrap(), 3);
+
+        let err = run_goose(&PathBuf::from("/nonexistent/goose"), &[]).unwrap_err();
+        assert!(matches!(err, CliError::GooseSpawn { .. }));
+        assert_eq!(err.exit_code(), EX_SOFTWARE);
+    }
+
+    #[test]
+    fn test_banner_enabled() {
+        assert!(banner_enabled(None));
+        assert!(banner_enabled(Some(OsString::new())));
+        assert!(!banner_enabled(Some(OsString::from("1"))));
+    }
+
+    #[test]
+    fn test_parse_timeout() {
+        assert_eq!(parse_timeout("2"), Ok(Duration::from_secs(2)));
+        assert_eq!(parse_timeout("0.25"), Ok(Duration::from_millis(250)));
+        for invalid in ["0", "-1", "soon", "inf", "NaN"] {
+            assert!(parse_timeout(invalid).is_err(), "{}", invalid);
+        }
+    }
+
     #[test]
     fn test_build_query_args_empty() {
         let query: Vec<String> = vec![];
-        let args = ChatArgs::build_query_args(&query);
+        let args = ChatArgs::build_query_args(&query, false, None, &[]);
 
         assert_eq!(args.len(), 2);
         assert_eq!(args[0], "run");
@@ -293,11 +1326,32 @@ mod tests {
     // Tests for Mode Detection Logic
     // ============================================================================
 
+    #[test]
+    fn test_repeat_last_without_previous_query() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("last_query");
+
+        assert!(matches!(
+            ChatArgs::last_query(Some(&path)),
+            Err(CliError::NoLastQuery)
+        ));
+        assert!(matches!(
+            ChatArgs::last_query(None),
+            Err(CliError::NoLastQuery)
+        ));
+
+        record_last_query(&path, "how do I list open ports?").unwrap();
+        assert_eq!(
+            ChatArgs::last_query(Some(&path)).unwrap(),
+            "how do I list open ports?"
+        );
+    }
+
     #[test]
     fn test_mode_detection_interactive() {
         let chat = ChatArgs {
             interactive: true,
-            query: vec![],
+            ..query_args(&[])
         };
 
         assert!(chat.interactive);
@@ -306,10 +1360,7 @@ mod tests {
 
     #[test]
     fn test_mode_detection_query() {
-        let chat = ChatArgs {
-            interactive: false,
-            query: vec!["test".to_string()],
-        };
+        let chat = query_args(&["test"]);
 
         assert!(!chat.interactive);
         assert!(!chat.query.is_empty());
@@ -317,10 +1368,7 @@ mod tests {
 
     #[test]
     fn test_mode_detection_no_args() {
-        let chat = ChatArgs {
-            interactive: false,
-            query: vec![],
-        };
+        let chat = query_args(&[]);
 
         assert!(!chat.interactive);
         assert!(chat.query.is_empty());
@@ -335,22 +1383,278 @@ mod tests {
         // Test that we can construct the ChatArgs struct manually
         let chat = ChatArgs {
             interactive: true,
-            query: vec![],
+            ..query_args(&[])
         };
 
-        assert_eq!(chat.interactive, true);
+        assert!(chat.interactive);
         assert_eq!(chat.query.len(), 0);
     }
 
     #[test]
     fn test_query_vector_operations() {
-        let chat = ChatArgs {
-            interactive: false,
-            query: vec!["test".to_string(), "query".to_string()],
-        };
+        let chat = query_args(&["test", "query"]);
 
         assert_eq!(chat.query.len(), 2);
         assert_eq!(chat.query[0], "test");
         assert_eq!(chat.query[1], "query");
     }
+
+    // ============================================================================
+    // Tests for Alias Expansion
+    // ============================================================================
+
+    fn query_args(query: &[&str]) -> ChatArgs {
+        ChatArgs {
+            interactive: false,
+            session: None,
+            resume: false,
+            check_backend: false,
+            precheck: false,
+            timeout: None,
+            json: false,
+            raw: false,
+            explain: false,
+            context: false,
+            no_context: false,
+            attach: vec![],
+            editor: false,
+            continue_conversation: false,
+            safe: false,
+            format: OutputFormat::Plain,
+            output: None,
+            append: false,
+            repeat_last: false,
+            query: query.iter().map(|word| word.to_string()).collect(),
+        }
+    }
+
+    fn aliases(aliases: &[(&str, &str)]) -> CliConfig {
+        CliConfig {
+            aliases: aliases
+                .iter()
+                .map(|(name, expansion)| (name.to_string(), expansion.to_string()))
+                .collect(),
+            ..CliConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_expand_alias_single_word() {
+        let mut chat = query_args(&["ll"]);
+
+        chat.expand_alias(&aliases(&[("ll", "list files in long format")]));
+
+        assert_eq!(chat.query, vec!["list files in long format"]);
+        assert_eq!(
+            ChatArgs::build_query_args(&chat.query, false, None, &[]),
+            vec!["run", "-t", "list files in long format"]
+        );
+    }
+
+    #[test]
+    fn test_expand_alias_ignores_multi_word_queries() {
+        let mut chat = query_args(&["ll", "in", "/tmp"]);
+
+        chat.expand_alias(&aliases(&[("ll", "list files in long format")]));
+
+        assert_eq!(chat.query, vec!["ll", "in", "/tmp"]);
+    }
+
+    #[test]
+    fn test_expand_alias_cannot_shadow_goose_subcommand() {
+        let mut chat = query_args(&["session"]);
+
+        chat.expand_alias(&aliases(&[("session", "what is a login session")]));
+
+        assert_eq!(chat.query, vec!["session"]);
+        assert!(is_goose_subcommand(&chat.query[0]));
+    }
+
+    #[test]
+    fn test_expand_alias_into_goose_subcommand_is_still_rejected() {
+        let mut chat = query_args(&["cfg"]);
+
+        chat.expand_alias(&aliases(&[("cfg", "configure")]));
+
+        // The subcommand guard in execute() sees the expansion
+        assert!(is_goose_subcommand(&chat.query[0]));
+    }
+
+    #[test]
+    fn test_misplaced_output_option_is_detected() {
+        for (query, expected) in [
+            (
+                &["generate a unit", "--output", "nginx.service"][..],
+                Some("--output"),
+            ),
+            (
+                &["generate a unit", "--output=nginx.service"][..],
+                Some("--output"),
+            ),
+            (&["generate a unit", "--append"][..], Some("--append")),
+            (&["what does --output do in tar"][..], None),
+            (&["generate a unit"][..], None),
+        ] {
+            assert_eq!(query_args(query).misplaced_output_option(), expected);
+        }
+    }
+
+    // ============================================================================
+    // Tests for captured output (--json)
+    // ============================================================================
+
+    #[test]
+    fn test_read_bounded_keeps_everything_under_limit() {
+        let (kept, truncated) = read_bounded(io::Cursor::new(b"hello"), 16).unwrap();
+
+        assert_eq!(kept, b"hello");
+        assert!(!truncated);
+    }
+
+    #[test]
+    fn test_read_bounded_drops_output_past_limit() {
+        let input = vec![b'x'; 20_000];
+
+        let (kept, truncated) = read_bounded(io::Cursor::new(input), 10_000).unwrap();
+
+        assert_eq!(kept.len(), 10_000);
+        assert!(truncated);
+    }
+
+    /// Writer whose reader has gone away
+    struct ClosedPipe;
+
+    impl Write for ClosedPipe {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::ErrorKind::BrokenPipe.into())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_write_output_ignores_closed_pipe() {
+        let mut out = Vec::new();
+        write_output(&mut out, "answer\n").unwrap();
+        assert_eq!(out, b"answer\n");
+
+        assert!(write_output(&mut ClosedPipe, "answer\n").is_ok());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_capture_goose_returns_output_and_exit_code() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let goose = temp_dir.path().join("goose");
+        fs::write(&goose, "#!/bin/sh\necho \"$@\"\nexit 3\n").unwrap();
+        fs::set_permissions(&goose, fs::Permissions::from_mode(0o755)).unwrap();
+        let query = vec!["hello".to_string(), "world".to_string()];
+
+        let output = capture_goose(
+            &goose,
+            &ChatArgs::build_query_args(&query, false, None, &[]),
+            MAX_CAPTURED_OUTPUT,
+        )
+        .unwrap();
+
+        assert_eq!(output.stdout, "run -t hello world\n");
+        assert_eq!(output.exit_code, 3);
+        assert!(!output.truncated);
+        assert_eq!(
+            json_result(&query, &output),
+            serde_json::json!({
+                "query": ["hello", "world"],
+                "response": "run -t hello world\n",
+                "exit_code": 3,
+                "truncated": false
+            })
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_execute_captured_returns_goose_exit_code() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let goose = temp_dir.path().join("goose");
+        fs::write(&goose, "#!/bin/sh\necho 'sudo rm -rf /var/log/*'\nexit 2\n").unwrap();
+        fs::set_permissions(&goose, fs::Permissions::from_mode(0o755)).unwrap();
+        let dangerous = DangerousPatterns::from_config(None).unwrap();
+
+        let result = ChatArgs::execute_captured(
+            &goose,
+            &["run".to_string()],
+            Some(&dangerous),
+            true,
+            false,
+            None,
+        );
+
+        assert_eq!(result.unwrap(), 2);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_execute_captured_saves_only_successful_answers() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let goose = temp_dir.path().join("goose");
+        let saved = temp_dir.path().join("nginx.service");
+        let answer = |script: &str, append: bool| {
+            fs::write(&goose, script).unwrap();
+            fs::set_permissions(&goose, fs::Permissions::from_mode(0o755)).unwrap();
+            ChatArgs::execute_captured(
+                &goose,
+                &["run".to_string()],
+                None,
+                false,
+                false,
+                Some((&saved, append)),
+            )
+            .unwrap()
+        };
+
+        assert_eq!(answer("#!/bin/sh\necho '[Unit]'\n", false), 0);
+        assert_eq!(answer("#!/bin/sh\necho '[Service]'\n", true), 0);
+        assert_eq!(answer("#!/bin/sh\necho 'backend down'\nexit 1\n", false), 1);
+
+        assert_eq!(fs::read_to_string(&saved).unwrap(), "[Unit]\n[Service]\n");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_execute_captured_numbers_explained_answers() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let goose = temp_dir.path().join("goose");
+        let saved = temp_dir.path().join("steps.txt");
+        fs::write(
+            &goose,
+            "#!/bin/sh\nprintf -- '- Stop the service\\n- Start it again\\n'\n",
+        )
+        .unwrap();
+        fs::set_permissions(&goose, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let result = ChatArgs::execute_captured(
+            &goose,
+            &["run".to_string()],
+            None,
+            false,
+            true,
+            Some((&saved, false)),
+        );
+
+        assert_eq!(result.unwrap(), 0);
+        assert_eq!(
+            fs::read_to_string(&saved).unwrap(),
+            "1. Stop the service\n2. Start it again\n"
+        );
+    }
 }
@@ -0,0 +1,171 @@
+//! Config command implementation
+//!
+//! This module shows where the CLI keeps its goose configuration and
+//! regenerates the default config.yaml.
+
+use clap::{Args, Subcommand};
+use log::error;
+use std::fs;
+use std::path::Path;
+
+use crate::color::{self, Palette, Style};
+use crate::error::CliError;
+use crate::helpers::{
+    config_value, config_yaml_template, find_goose, goose_config_dir, write_goose_config_files,
+};
+
+/// Show and manage the effective configuration
+#[derive(Args, Debug)]
+pub struct ConfigArgs {
+    /// Config subcommand to execute
+    #[command(subcommand)]
+    pub command: ConfigCommands,
+}
+
+/// Available config subcommands
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommands {
+    /// Show the resolved configuration
+    Show,
+
+    /// Print the config directory
+    Path,
+
+    /// Create the default config.yaml if it is missing
+    Init {
+        /// Overwrite an existing config.yaml
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+/// Names of the config subcommands, used when routing arguments
+pub const CONFIG_SUBCOMMANDS: &[&str] = &["show", "path", "init"];
+
+impl ConfigArgs {
+    /// Execute the config command
+    pub fn execute(&self) -> Result<i32, CliError> {
+        let config_dir = goose_config_dir().map_err(|e| {
+            error!("Failed to resolve config directory: {:#}", e);
+            CliError::ConfigDir(e)
+        })?;
+
+        match self.command {
+            ConfigCommands::Show => {
+                let goose = find_goose().ok();
+                print!(
+                    "{}",
+                    render_show(&config_dir, goose.as_deref(), color::palette())
+                );
+            }
+            ConfigCommands::Path => println!("{}", config_dir.display()),
+            ConfigCommands::Init { force } => match config_yaml_template()
+                .and_then(|template| write_goose_config_files(&config_dir, &template, force))
+            {
+                Ok(true) => println!("Wrote {}", config_dir.join("config.yaml").display()),
+                Ok(false) => println!(
+                    "{} already exists; use --force to overwrite it",
+                    config_dir.join("config.yaml").display()
+                ),
+                Err(e) => {
+                    error!("Failed to write config files: {:#}", e);
+                    return Err(CliError::ConfigWrite(e));
+                }
+            },
+        }
+        Ok(0)
+    }
+}
+
+/// Render the `c config show` report
+fn render_show(config_dir: &Path, goose: Option<&Path>, palette: Palette) -> String {
+    let config_yaml_path = config_dir.join("config.yaml");
+    let config_yaml = fs::read_to_string(&config_yaml_path).ok();
+    let value = |key| {
+        config_yaml
+            .as_deref()
+            .and_then(|content| config_value(content, key))
+            .unwrap_or("(not set)")
+    };
+    let line = |label: &str, value: String| {
+        format!(
+            "{} {}\n",
+            palette.paint(&format!("{}:", label), Style::Bold),
+            value
+        )
+    };
+
+    let config_yaml_state = if config_yaml.is_some() {
+        palette.paint("present", Style::Green)
+    } else {
+        palette.paint("missing", Style::Red)
+    };
+    let goose = match goose {
+        Some(path) => path.display().to_string(),
+        None => palette.paint("not found", Style::Red),
+    };
+
+    let mut report = line("Config directory", config_dir.display().to_string());
+    report.push_str(&line(
+        "config.yaml",
+        format!("{} ({})", config_yaml_state, config_yaml_path.display()),
+    ));
+    report.push_str(&line("Model", value("GOOSE_MODEL").to_string()));
+    report.push_str(&line("Provider", value("GOOSE_PROVIDER").to_string()));
+    report.push_str(&line("Goose binary", goose));
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::DEFAULT_CONFIG_YAML;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_render_show_with_default_config() {
+        let temp_dir = TempDir::new().unwrap();
+        write_goose_config_files(temp_dir.path(), DEFAULT_CONFIG_YAML, false).unwrap();
+
+        let report = render_show(
+            temp_dir.path(),
+            Some(Path::new("/usr/bin/goose")),
+            Palette::PLAIN,
+        );
+
+        assert!(report.contains(&format!("Config directory: {}", temp_dir.path().display())));
+        assert!(report.contains("config.yaml: present"));
+        assert!(report.contains("Model: default-model"));
+        assert!(report.contains("Provider: ollama"));
+        assert!(report.contains("Goose binary: /usr/bin/goose"));
+    }
+
+    #[test]
+    fn test_render_show_without_config() {
+        let temp_dir = TempDir::new().unwrap();
+
+        let report = render_show(temp_dir.path(), None, Palette::PLAIN);
+
+        assert!(report.contains("config.yaml: missing"));
+        assert!(report.contains("Model: (not set)"));
+        assert!(report.contains("Goose binary: not found"));
+    }
+
+    #[test]
+    #[allow(unsafe_code)]
+    fn test_render_show_honors_no_color() {
+        let temp_dir = TempDir::new().unwrap();
+
+        // Detected as for a terminal, so only NO_COLOR can disable color
+        let palette = unsafe {
+            std::env::set_var("NO_COLOR", "1");
+            let palette = Palette::detect(false, true);
+            std::env::remove_var("NO_COLOR");
+            palette
+        };
+        let report = render_show(temp_dir.path(), None, palette);
+
+        assert!(!report.contains('\x1b'));
+        assert!(report.contains("Goose binary: not found"));
+    }
+}
@@ -13,7 +13,7 @@ pub struct HistoryArgs {
 
     /// Show full details for each entry
     #[arg(short, long)]
-    pub verbose: bool,
+    pub full: bool,
 
     /// Limit number of entries to show
     #[arg(short = 'n', long, default_value = "10")]
@@ -29,11 +29,11 @@ impl HistoryArgs {
             println!("Listing recent history entries (limit: {})...", self.limit);
         }
 
-        if self.verbose {
-            println!("Verbose mode enabled - showing full details");
+        if self.full {
+            println!("Showing full details");
         }
 
-        if !self.list && !self.verbose {
+        if !self.list && !self.full {
             println!("This command will show your chat history.");
             println!("Use --help to see available options.");
         }
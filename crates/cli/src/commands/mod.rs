@@ -3,5 +3,6 @@
 //! This module contains all command handlers for the application.
 
 pub mod chat;
+pub mod config;
 pub mod history;
 pub mod shell;
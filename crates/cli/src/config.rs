@@ -1,5 +1,11 @@
-use etcetera::AppStrategyArgs;
+use anyhow::{Context, Result};
+use etcetera::{choose_base_strategy, AppStrategyArgs, BaseStrategy};
 use once_cell::sync::Lazy;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
 
 /// Goose application strategy configuration for determining config directory paths
 pub static GOOSE_APP_STRATEGY: Lazy<AppStrategyArgs> = Lazy::new(|| AppStrategyArgs {
@@ -8,9 +14,105 @@ pub static GOOSE_APP_STRATEGY: Lazy<AppStrategyArgs> = Lazy::new(|| AppStrategyA
     app_name: "goose".to_string(),
 });
 
+/// Directory of the CLI's own settings, inside the user config directory
+const CLI_CONFIG_DIR: &str = "command-line-assistant";
+
+/// Settings file of the CLI, inside [`CLI_CONFIG_DIR`]
+const CLI_CONFIG_FILE: &str = "cli.toml";
+
+/// Banner printed when an interactive session starts, unless configured
+pub const DEFAULT_BANNER: &str = "Command Line Assistant — type your question, Ctrl-D to exit";
+
+/// Settings of the CLI itself, read from `cli.toml`
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CliConfig {
+    /// Single-word queries expanded to a full query, e.g. `ll = "list files in long format"`
+    #[serde(default)]
+    pub aliases: BTreeMap<String, String>,
+    /// Add the OS and kernel versions to queries, as with `--context`
+    #[serde(default)]
+    pub context: bool,
+    /// Regular expressions of commands flagged by `--safe`, replacing the
+    /// built-in list
+    #[serde(default)]
+    pub safe_patterns: Option<Vec<String>>,
+    /// Text printed on stderr when an interactive session starts, replacing
+    /// [`DEFAULT_BANNER`]; an empty one prints nothing
+    #[serde(default)]
+    pub banner: Option<String>,
+    /// File whose contents are printed instead of `banner`
+    #[serde(default)]
+    pub banner_file: Option<PathBuf>,
+    /// Keep the text of the last answered query for `--repeat-last`; on
+    /// unless set to `false`
+    #[serde(default)]
+    pub remember_last_query: Option<bool>,
+}
+
+impl CliConfig {
+    /// Path of `cli.toml` in the user config directory
+    pub fn path() -> Result<PathBuf> {
+        let base = choose_base_strategy().context(
+            "Failed to determine config directory (HOME environment variable may not be set)",
+        )?;
+        Ok(base.config_dir().join(CLI_CONFIG_DIR).join(CLI_CONFIG_FILE))
+    }
+
+    /// Load `cli.toml`, using the defaults when it does not exist
+    pub fn load() -> Result<Self> {
+        Self::from_file(&Self::path()?)
+    }
+
+    /// Load the settings from `path`, using the defaults when it does not exist
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {:?}", path)),
+        };
+        toml::from_str(&content).with_context(|| format!("Failed to parse {:?}", path))
+    }
+
+    /// Banner for interactive sessions, `None` when it is empty
+    ///
+    /// `banner_file` is read when set, otherwise `banner` or the default
+    /// banner is used.
+    pub fn banner(&self) -> Result<Option<String>> {
+        let banner = match (&self.banner_file, &self.banner) {
+            (Some(path), _) => fs::read_to_string(path)
+                .with_context(|| format!("Failed to read banner file {:?}", path))?,
+            (None, Some(banner)) => banner.clone(),
+            (None, None) => DEFAULT_BANNER.to_string(),
+        };
+        let banner = banner.trim_end();
+        Ok((!banner.is_empty()).then(|| banner.to_string()))
+    }
+
+    /// Whether the last answered query is kept for `--repeat-last`
+    pub fn remember_last_query(&self) -> bool {
+        self.remember_last_query.unwrap_or(true)
+    }
+
+    /// Expansion of `query` when it is a single word naming an alias
+    ///
+    /// Multi-word queries are never aliased.
+    pub fn expand_alias(&self, query: &[String]) -> Option<&str> {
+        match query {
+            [word] => self.aliases.get(word).map(String::as_str),
+            _ => None,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use tempfile::TempDir;
+
+    fn query(words: &[&str]) -> Vec<String> {
+        words.iter().map(|word| word.to_string()).collect()
+    }
 
     #[test]
     fn test_goose_app_strategy_initialization() {
@@ -32,4 +134,109 @@ mod tests {
         assert_eq!(strategy1.author, strategy2.author);
         assert_eq!(strategy1.top_level_domain, strategy2.top_level_domain);
     }
+
+    #[test]
+    fn test_cli_config_missing_file_has_no_aliases() {
+        let config = CliConfig::from_file(Path::new("/nonexistent/cli.toml")).unwrap();
+
+        assert!(config.aliases.is_empty());
+        assert!(!config.context);
+    }
+
+    #[test]
+    fn test_cli_config_aliases() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("cli.toml");
+        fs::write(&path, "[aliases]\nll = \"list files in long format\"\n").unwrap();
+
+        let config = CliConfig::from_file(&path).unwrap();
+
+        assert_eq!(
+            config.expand_alias(&query(&["ll"])),
+            Some("list files in long format")
+        );
+        assert_eq!(config.expand_alias(&query(&["ls"])), None);
+        assert_eq!(config.expand_alias(&query(&["ll", "please"])), None);
+        assert_eq!(config.expand_alias(&[]), None);
+    }
+
+    #[test]
+    fn test_cli_config_context_default() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("cli.toml");
+        fs::write(&path, "context = true\n").unwrap();
+
+        let config = CliConfig::from_file(&path).unwrap();
+
+        assert!(config.context);
+    }
+
+    #[test]
+    fn test_cli_config_safe_patterns() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("cli.toml");
+        fs::write(&path, "safe_patterns = ['\\brm\\s+-rf\\b']\n").unwrap();
+
+        let config = CliConfig::from_file(&path).unwrap();
+
+        assert_eq!(
+            config.safe_patterns,
+            Some(vec![r"\brm\s+-rf\b".to_string()])
+        );
+        assert_eq!(CliConfig::default().safe_patterns, None);
+    }
+
+    #[test]
+    fn test_cli_config_banner() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("motd");
+        fs::write(&path, "Welcome to the lab\n\n").unwrap();
+        let config = |content: String| toml::from_str::<CliConfig>(&content).unwrap();
+
+        assert_eq!(
+            CliConfig::default().banner().unwrap().as_deref(),
+            Some(DEFAULT_BANNER)
+        );
+        assert_eq!(
+            config("banner = \"Ask away\"".into()).banner().unwrap(),
+            Some("Ask away".to_string())
+        );
+        assert_eq!(config("banner = \"\"".into()).banner().unwrap(), None);
+        assert_eq!(
+            config(format!("banner = \"ignored\"\nbanner_file = {:?}", path))
+                .banner()
+                .unwrap(),
+            Some("Welcome to the lab".to_string())
+        );
+        assert!(config("banner_file = \"/nonexistent/motd\"".into())
+            .banner()
+            .is_err());
+    }
+
+    #[test]
+    fn test_cli_config_remember_last_query() {
+        let config = |content: &str| toml::from_str::<CliConfig>(content).unwrap();
+
+        assert!(CliConfig::default().remember_last_query());
+        assert!(config("remember_last_query = true").remember_last_query());
+        assert!(!config("remember_last_query = false").remember_last_query());
+    }
+
+    #[test]
+    fn test_cli_config_rejects_unknown_keys() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("cli.toml");
+        fs::write(&path, "[alias]\nll = \"list files\"\n").unwrap();
+
+        let err = CliConfig::from_file(&path).unwrap_err();
+
+        assert!(format!("{:#}", err).contains("unknown field"), "{:#}", err);
+    }
+
+    #[test]
+    fn test_cli_config_path() {
+        let path = CliConfig::path().unwrap();
+
+        assert!(path.ends_with("command-line-assistant/cli.toml"));
+    }
 }
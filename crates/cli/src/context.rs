@@ -0,0 +1,172 @@
+//! System facts added to queries by `--context`
+//!
+//! Only the OS name and version from os-release and the kernel release are
+//! gathered, never host names, user names or paths. The facts are read once
+//! per run and reused.
+
+use log::debug;
+use once_cell::sync::OnceCell;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// File describing the installed OS
+const OS_RELEASE: &str = "/etc/os-release";
+
+/// Kernel release, as printed by `uname -r`
+const KERNEL_RELEASE: &str = "/proc/sys/kernel/osrelease";
+
+/// Longest value kept for a single fact, in characters
+const MAX_FACT_LEN: usize = 128;
+
+/// Context sentence, computed on first use
+static SYSTEM_CONTEXT: OnceCell<Option<String>> = OnceCell::new();
+
+/// Sentence describing the OS and kernel, or `None` when neither is known
+pub fn system_context() -> Option<&'static str> {
+    SYSTEM_CONTEXT
+        .get_or_init(|| {
+            let context = build_context(
+                read_os_name(Path::new(OS_RELEASE)).as_deref(),
+                kernel_release().as_deref(),
+            );
+            debug!("System context: {:?}", context);
+            context
+        })
+        .as_deref()
+}
+
+/// Build the context sentence from the gathered facts
+fn build_context(os: Option<&str>, kernel: Option<&str>) -> Option<String> {
+    let system = match (os, kernel) {
+        (Some(os), Some(kernel)) => format!("{} with kernel {}", os, kernel),
+        (Some(os), None) => os.to_string(),
+        (None, Some(kernel)) => format!("kernel {}", kernel),
+        (None, None) => return None,
+    };
+    Some(format!("Context: my system runs {}.", system))
+}
+
+/// OS name from an os-release file
+///
+/// `PRETTY_NAME` is preferred, falling back to `NAME` and `VERSION_ID`.
+fn read_os_name(path: &Path) -> Option<String> {
+    let content = fs::read_to_string(path).ok()?;
+    let field = |key: &str| {
+        content.lines().find_map(|line| {
+            line.strip_prefix(key)
+                .and_then(|rest| rest.strip_prefix('='))
+                .and_then(clean)
+        })
+    };
+
+    field("PRETTY_NAME").or_else(|| match (field("NAME"), field("VERSION_ID")) {
+        (Some(name), Some(version)) => Some(format!("{} {}", name, version)),
+        (name, _) => name,
+    })
+}
+
+/// Kernel release, from procfs or `uname -r`
+fn kernel_release() -> Option<String> {
+    fs::read_to_string(KERNEL_RELEASE)
+        .ok()
+        .or_else(|| {
+            let output = Command::new("uname").arg("-r").output().ok()?;
+            output
+                .status
+                .success()
+                .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+        })
+        .and_then(|release| clean(&release))
+}
+
+/// Unquote a value and drop control characters, capping its length
+fn clean(value: &str) -> Option<String> {
+    let value = value.trim();
+    let value = value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+        .unwrap_or(value);
+    let value: String = value
+        .chars()
+        .filter(|c| !c.is_control())
+        .take(MAX_FACT_LEN)
+        .collect();
+    let value = value.trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const RHEL_OS_RELEASE: &str = r#"NAME="Red Hat Enterprise Linux"
+VERSION="9.4 (Plow)"
+ID="rhel"
+VERSION_ID="9.4"
+PRETTY_NAME="Red Hat Enterprise Linux 9.4 (Plow)"
+HOME_URL="https://www.redhat.com/"
+"#;
+
+    fn os_release(content: &str) -> (TempDir, std::path::PathBuf) {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("os-release");
+        fs::write(&path, content).unwrap();
+        (temp_dir, path)
+    }
+
+    #[test]
+    fn test_read_os_name_prefers_pretty_name() {
+        let (_temp_dir, path) = os_release(RHEL_OS_RELEASE);
+
+        assert_eq!(
+            read_os_name(&path).as_deref(),
+            Some("Red Hat Enterprise Linux 9.4 (Plow)")
+        );
+    }
+
+    #[test]
+    fn test_read_os_name_falls_back_to_name_and_version() {
+        let (_temp_dir, path) = os_release("NAME=Fedora\nVERSION_ID=40\nID=fedora\n");
+
+        assert_eq!(read_os_name(&path).as_deref(), Some("Fedora 40"));
+    }
+
+    #[test]
+    fn test_read_os_name_missing_file() {
+        assert_eq!(read_os_name(Path::new("/nonexistent/os-release")), None);
+    }
+
+    #[test]
+    fn test_build_context_from_fixture() {
+        let (_temp_dir, path) = os_release(RHEL_OS_RELEASE);
+
+        let context = build_context(
+            read_os_name(&path).as_deref(),
+            Some("5.14.0-427.el9.x86_64"),
+        );
+
+        assert_eq!(
+            context.as_deref(),
+            Some(
+                "Context: my system runs Red Hat Enterprise Linux 9.4 (Plow) \
+                 with kernel 5.14.0-427.el9.x86_64."
+            )
+        );
+        assert_eq!(
+            build_context(None, Some("6.8.0")).as_deref(),
+            Some("Context: my system runs kernel 6.8.0.")
+        );
+        assert_eq!(build_context(None, None), None);
+    }
+
+    #[test]
+    fn test_clean_strips_quotes_and_control_characters() {
+        assert_eq!(clean("\"Fedora\nLinux\"").as_deref(), Some("FedoraLinux"));
+        assert_eq!(clean("'Fedora\u{1b}[31m'").as_deref(), Some("Fedora[31m"));
+        assert_eq!(clean("  \"\"  "), None);
+        assert_eq!(clean(&"x".repeat(500)).map(|v| v.len()), Some(MAX_FACT_LEN));
+    }
+}
@@ -0,0 +1,202 @@
+//! Errors ending a command, and the exit codes they map to
+//!
+//! Commands return a [`CliError`] instead of exiting, so that the dispatcher
+//! reports it and exits in a single place. Exit codes follow sysexits.h.
+
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+use crate::helpers::{
+    ConfigLockTimeout, EX_CANTCREAT, EX_CONFIG, EX_DATAERR, EX_NOINPUT, EX_OSERR, EX_SOFTWARE,
+    EX_TEMPFAIL, EX_UNAVAILABLE,
+};
+use crate::output::advise;
+
+/// Why a command failed
+#[derive(Debug)]
+pub enum CliError {
+    /// The arguments can't be used, such as a missing query
+    Usage(String),
+    /// The goose config files could not be created
+    ConfigSetup(anyhow::Error),
+    /// The goose config directory could not be determined
+    ConfigDir(anyhow::Error),
+    /// config.yaml could not be written
+    ConfigWrite(anyhow::Error),
+    /// No goose binary was found
+    GooseNotFound,
+    /// The goose binary could not be started
+    GooseSpawn {
+        /// Path of the goose binary
+        goose: PathBuf,
+        /// Error starting it
+        source: io::Error,
+    },
+    /// Waiting for goose to finish failed
+    GooseWait(io::Error),
+    /// The backend is rate limiting, with the seconds to wait when known
+    RateLimited(Option<u64>),
+    /// A file given to `--attach` does not exist
+    MissingAttachment(PathBuf),
+    /// A file given to `--attach` can't be attached
+    Attachment(anyhow::Error),
+    /// The editor opened by `--editor` failed
+    Editor(anyhow::Error),
+    /// The query saved in the editor is empty
+    EmptyQuery,
+    /// `--repeat-last` found no previous query
+    NoLastQuery,
+    /// A `safe_patterns` entry in cli.toml is not a valid regular expression
+    SafePatterns(anyhow::Error),
+    /// The answer can't be saved to the `--output` file
+    Output(anyhow::Error),
+}
+
+impl CliError {
+    /// Exit code for the error
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            CliError::Usage(_) | CliError::GooseSpawn { .. } | CliError::Editor(_) => EX_SOFTWARE,
+            // Another process held the config lock, trying again may work
+            CliError::ConfigSetup(e) | CliError::ConfigWrite(e) if e.is::<ConfigLockTimeout>() => {
+                EX_TEMPFAIL
+            }
+            CliError::ConfigSetup(_)
+            | CliError::ConfigDir(_)
+            | CliError::ConfigWrite(_)
+            | CliError::Output(_) => EX_CANTCREAT,
+            CliError::GooseNotFound => EX_UNAVAILABLE,
+            CliError::GooseWait(_) => EX_OSERR,
+            CliError::RateLimited(_) => EX_TEMPFAIL,
+            CliError::MissingAttachment(_) | CliError::NoLastQuery => EX_NOINPUT,
+            CliError::Attachment(_) | CliError::EmptyQuery => EX_DATAERR,
+            CliError::SafePatterns(_) => EX_CONFIG,
+        }
+    }
+
+    /// Print the error to stderr, with advice on fixing it
+    pub fn report(&self) {
+        eprintln!("{}", self);
+        match self {
+            CliError::ConfigSetup(e) if !e.is::<ConfigLockTimeout>() => {
+                advise("This may be due to insufficient permissions or disk space.")
+            }
+            CliError::GooseNotFound => {
+                #[cfg(unix)]
+                advise("Please ensure goose is installed at /usr/bin/goose");
+                #[cfg(windows)]
+                advise("Please ensure goose.exe is on your PATH");
+                advise("Or set GOOSE_BINARY environment variable to the correct path");
+            }
+            CliError::GooseSpawn { goose, .. } => advise(format!("Command: {:?}", goose)),
+            _ => {}
+        }
+    }
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Usage(message) => write!(f, "Error: {}", message),
+            CliError::ConfigSetup(e) => write!(f, "Error setting up configuration: {}", e),
+            CliError::ConfigDir(e) => write!(f, "Error: {}", e),
+            CliError::ConfigWrite(e) => write!(f, "Error writing configuration: {}", e),
+            CliError::GooseNotFound => write!(f, "Error: goose binary not found"),
+            CliError::GooseSpawn { source, .. } => write!(f, "Error executing goose: {}", source),
+            CliError::GooseWait(e) => write!(f, "Error waiting for goose process: {}", e),
+            CliError::RateLimited(Some(seconds)) => write!(
+                f,
+                "You're being rate limited; try again in {} seconds.",
+                seconds
+            ),
+            CliError::RateLimited(None) => write!(f, "You're being rate limited; try again later."),
+            CliError::MissingAttachment(path) => {
+                write!(f, "Error: {} does not exist", path.display())
+            }
+            CliError::Attachment(e)
+            | CliError::Editor(e)
+            | CliError::SafePatterns(e)
+            | CliError::Output(e) => write!(f, "Error: {:#}", e),
+            CliError::EmptyQuery => write!(f, "Aborting: the query is empty"),
+            CliError::NoLastQuery => write!(f, "Error: there is no previous query to repeat"),
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_codes() {
+        let cases = [
+            (CliError::Usage("no query".to_string()), EX_SOFTWARE),
+            (
+                CliError::ConfigSetup(anyhow::anyhow!("disk full")),
+                EX_CANTCREAT,
+            ),
+            (CliError::GooseNotFound, EX_UNAVAILABLE),
+            (
+                CliError::GooseSpawn {
+                    goose: PathBuf::from("/usr/bin/goose"),
+                    source: io::Error::from(io::ErrorKind::PermissionDenied),
+                },
+                EX_SOFTWARE,
+            ),
+            (
+                CliError::GooseWait(io::Error::from(io::ErrorKind::Other)),
+                EX_OSERR,
+            ),
+            (CliError::RateLimited(Some(30)), EX_TEMPFAIL),
+            (
+                CliError::ConfigSetup(
+                    ConfigLockTimeout {
+                        path: PathBuf::from(".config.lock"),
+                        timeout: std::time::Duration::from_secs(30),
+                    }
+                    .into(),
+                ),
+                EX_TEMPFAIL,
+            ),
+            (
+                CliError::MissingAttachment(PathBuf::from("a.txt")),
+                EX_NOINPUT,
+            ),
+            (CliError::Attachment(anyhow::anyhow!("binary")), EX_DATAERR),
+            (CliError::EmptyQuery, EX_DATAERR),
+            (CliError::NoLastQuery, EX_NOINPUT),
+            (
+                CliError::SafePatterns(anyhow::anyhow!("bad regex")),
+                EX_CONFIG,
+            ),
+            (CliError::Output(anyhow::anyhow!("read-only")), EX_CANTCREAT),
+        ];
+
+        for (error, code) in cases {
+            assert_eq!(error.exit_code(), code, "{:?}", error);
+        }
+    }
+
+    #[test]
+    fn test_messages_match_previous_output() {
+        assert_eq!(
+            CliError::Usage("Direct goose subcommands are not supported".to_string()).to_string(),
+            "Error: Direct goose subcommands are not supported"
+        );
+        assert_eq!(
+            CliError::RateLimited(Some(30)).to_string(),
+            "You're being rate limited; try again in 30 seconds."
+        );
+        assert_eq!(
+            CliError::RateLimited(None).to_string(),
+            "You're being rate limited; try again later."
+        );
+        assert_eq!(
+            CliError::MissingAttachment(PathBuf::from("a.txt")).to_string(),
+            "Error: a.txt does not exist"
+        );
+    }
+}
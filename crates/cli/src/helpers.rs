@@ -8,16 +8,34 @@ use etcetera::{choose_app_strategy, AppStrategy};
 use fs2::FileExt;
 use log::{debug, info, warn};
 use std::env;
-use std::fs;
-use std::io::Write;
-use std::os::unix::fs::PermissionsExt;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
 use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
 use tempfile::NamedTempFile;
 
 use crate::config::GOOSE_APP_STRATEGY;
+use crate::output::advise;
 
+#[cfg(unix)]
 pub const DEFAULT_PATHS: &[&str] = &["/usr/bin/goose"];
 
+/// Windows has no standard install location, goose is looked up on `PATH`
+#[cfg(windows)]
+pub const DEFAULT_PATHS: &[&str] = &[];
+
+/// File name of the goose binary looked up on `PATH`
+#[cfg(windows)]
+pub const GOOSE_EXECUTABLE: &str = "goose.exe";
+
+/// Extensions Windows runs directly, compared case-insensitively
+#[cfg(windows)]
+const WINDOWS_EXECUTABLE_EXTENSIONS: &[&str] = &["exe", "cmd", "bat"];
+
 pub const GOOSE_SUBCOMMANDS: &[&str] = &[
     "configure",
     "info",
@@ -42,11 +60,29 @@ pub const GOOSE_SUBCOMMANDS: &[&str] = &[
 pub const MAX_ARG_LENGTH: usize = 1_000_000; // 1MB per argument
 pub const MAX_TOTAL_ARGS_LENGTH: usize = 10_000_000; // 10MB total
 
+/// Longest single argument Linux passes to a program (`MAX_ARG_STRLEN`, 32
+/// pages), terminating NUL excluded; longer ones fail with E2BIG
+pub const MAX_ARG_STRLEN: usize = 128 * 1024 - 1;
+
+/// Longest argument passed on to goose
+pub const MAX_GOOSE_ARG_LENGTH: usize = if MAX_ARG_LENGTH < MAX_ARG_STRLEN {
+    MAX_ARG_LENGTH
+} else {
+    MAX_ARG_STRLEN
+};
+
+/// Maximum length of a goose session name
+pub const MAX_SESSION_NAME_LENGTH: usize = 128;
+
 /// Exit codes following sysexits.h convention
+pub const EX_DATAERR: i32 = 65; // Input data was incorrect (unreadable attachment)
+pub const EX_NOINPUT: i32 = 66; // Input file missing
 pub const EX_UNAVAILABLE: i32 = 69; // Service unavailable (goose not found)
 pub const EX_SOFTWARE: i32 = 70; // Internal software error
 pub const EX_OSERR: i32 = 71; // System error
 pub const EX_CANTCREAT: i32 = 73; // Can't create output file
+pub const EX_TEMPFAIL: i32 = 75; // Temporary failure (rate limited)
+pub const EX_CONFIG: i32 = 78; // Configuration error (invalid safe_patterns)
 
 /// Validates that a path points to an executable file
 pub fn is_executable(path: &Path) -> bool {
@@ -60,7 +96,14 @@ pub fn is_executable(path: &Path) -> bool {
         return false;
     }
 
-    // Check Unix permissions for executable bit
+    has_execute_permission(path)
+}
+
+/// Check Unix permissions for an executable bit
+#[cfg(unix)]
+fn has_execute_permission(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
     match fs::metadata(path) {
         Ok(metadata) => {
             let permissions = metadata.permissions();
@@ -77,6 +120,21 @@ pub fn is_executable(path: &Path) -> bool {
     }
 }
 
+/// Windows has no executable bit, the extension decides what can be run
+#[cfg(windows)]
+fn has_execute_permission(path: &Path) -> bool {
+    let is_exec = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            WINDOWS_EXECUTABLE_EXTENSIONS
+                .iter()
+                .any(|known| extension.eq_ignore_ascii_case(known))
+        });
+    debug!("Path {:?} executable: {}", path, is_exec);
+    is_exec
+}
+
 /// Find the goose binary with proper validation
 pub fn find_goose() -> Result<PathBuf> {
     // Check environment variable first
@@ -106,6 +164,18 @@ pub fn find_goose() -> Result<PathBuf> {
             return Ok(path.to_path_buf());
         }
     }
+
+    #[cfg(windows)]
+    if let Some(path) = env::var_os("PATH")
+        .iter()
+        .flat_map(env::split_paths)
+        .map(|dir| dir.join(GOOSE_EXECUTABLE))
+        .find(|path| is_executable(path))
+    {
+        info!("Using goose from PATH: {:?}", path);
+        return Ok(path);
+    }
+
     bail!("Goose binary not found in environment variable or default paths")
 }
 
@@ -114,6 +184,27 @@ pub fn is_goose_subcommand(arg: &str) -> bool {
     GOOSE_SUBCOMMANDS.contains(&arg)
 }
 
+/// Validate the arguments goose is started with
+///
+/// On top of [`validate_args`], each argument must fit in
+/// [`MAX_GOOSE_ARG_LENGTH`], so goose can be started at all.
+pub fn validate_goose_args(args: &[String]) -> Result<()> {
+    validate_args(args)?;
+    if let Some((i, arg)) = args
+        .iter()
+        .enumerate()
+        .find(|(_, arg)| arg.len() > MAX_GOOSE_ARG_LENGTH)
+    {
+        bail!(
+            "Argument {} is too long: {} bytes (max: {})",
+            i,
+            arg.len(),
+            MAX_GOOSE_ARG_LENGTH
+        );
+    }
+    Ok(())
+}
+
 /// Validate command-line arguments for security and resource limits
 pub fn validate_args(args: &[String]) -> Result<()> {
     let mut total_length = 0;
@@ -158,11 +249,191 @@ pub fn validate_args(args: &[String]) -> Result<()> {
     Ok(())
 }
 
+/// Validate a session name before handing it to goose
+///
+/// Goose uses the name for a file in its sessions directory, so path
+/// separators are refused along with null bytes and overly long names.
+pub fn validate_session_name(name: &str) -> Result<()> {
+    if name.is_empty() {
+        bail!("Session name cannot be empty");
+    }
+
+    if name.len() > MAX_SESSION_NAME_LENGTH {
+        bail!(
+            "Session name is too long: {} bytes (max: {})",
+            name.len(),
+            MAX_SESSION_NAME_LENGTH
+        );
+    }
+
+    if name.contains('\0') {
+        bail!("Session name contains null byte");
+    }
+
+    if name.contains(['/', '\\']) || name == "." || name == ".." {
+        bail!("Session name cannot contain path separators");
+    }
+
+    if name.starts_with('-') {
+        bail!("Session name cannot start with '-'");
+    }
+
+    Ok(())
+}
+
+/// Read a text file attached to a query, refusing more than `limit` bytes
+///
+/// Binary files and files that aren't UTF-8 are refused, since their
+/// content would only reach the assistant mangled.
+pub fn read_attachment(path: &Path, limit: usize) -> Result<String> {
+    let mut file =
+        fs::File::open(path).with_context(|| format!("Cannot read {}", path.display()))?;
+    if !file.metadata()?.is_file() {
+        bail!("{} is not a regular file", path.display());
+    }
+
+    let mut content = Vec::new();
+    (&mut file)
+        .take(limit as u64 + 1)
+        .read_to_end(&mut content)
+        .with_context(|| format!("Cannot read {}", path.display()))?;
+    if content.len() > limit {
+        bail!(
+            "{} is too large to attach (max: {} bytes)",
+            path.display(),
+            limit
+        );
+    }
+    if content.contains(&0) {
+        bail!("{} is a binary file and can't be attached", path.display());
+    }
+    String::from_utf8(content)
+        .map_err(|_| anyhow::anyhow!("{} is not UTF-8 text and can't be attached", path.display()))
+}
+
+/// Editor used when neither `VISUAL` nor `EDITOR` is set
+#[cfg(unix)]
+pub const DEFAULT_EDITOR: &str = "vi";
+
+/// Editor used when neither `VISUAL` nor `EDITOR` is set
+#[cfg(windows)]
+pub const DEFAULT_EDITOR: &str = "notepad";
+
+/// Editor command from `VISUAL` or `EDITOR`, in that order, like git
+pub fn editor_command() -> String {
+    ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|value| !value.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_EDITOR.to_string())
+}
+
+/// Open `editor` on an empty temporary file and return the text saved in it
+///
+/// The file starts out holding `initial`. The editor command may carry
+/// arguments, such as `code --wait`. Fails when the editor can't be started
+/// or exits unsuccessfully.
+pub fn read_from_editor(editor: &str, initial: &str) -> Result<String> {
+    let mut words = editor.split_whitespace();
+    let program = words.next().context("The editor command is empty")?;
+    let mut file = tempfile::Builder::new()
+        .prefix("c-query-")
+        .suffix(".txt")
+        .tempfile()
+        .context("Failed to create temporary file")?;
+    file.write_all(initial.as_bytes())
+        .and_then(|()| file.flush())
+        .context("Failed to write temporary file")?;
+
+    debug!("Opening {:?} with {:?}", file.path(), editor);
+    let status = std::process::Command::new(program)
+        .args(words)
+        .arg(file.path())
+        .status()
+        .with_context(|| format!("Failed to start editor {:?}", editor))?;
+    if !status.success() {
+        bail!("Editor {:?} exited with {}", editor, status);
+    }
+
+    // Read by path, editors often replace the file instead of writing to it
+    fs::read_to_string(file.path()).context("Failed to read the edited query")
+}
+
+/// Check that an answer can be saved to `path` before asking for it
+///
+/// `path` must not be a directory, and a file must be creatable next to it.
+pub fn check_output_path(path: &Path) -> Result<()> {
+    if path.is_dir() {
+        bail!("{:?} is a directory", path);
+    }
+    let parent = parent_dir(path)?;
+    NamedTempFile::new_in(parent)
+        .map(drop)
+        .with_context(|| format!("Can't write to {:?}", parent))
+}
+
+/// Save `answer` to `path`, replacing it or, with `append`, adding to its end
+///
+/// Replacing is atomic, so an interrupted write never leaves half an answer.
+/// A symlink is followed and the file it points to replaced, keeping the
+/// permissions of the file being replaced.
+pub fn save_answer(path: &Path, answer: &str, append: bool) -> Result<()> {
+    if !append {
+        return replace_file(path, answer).with_context(|| format!("Failed to write {:?}", path));
+    }
+    fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(answer.as_bytes()))
+        .with_context(|| format!("Failed to append to {:?}", path))
+}
+
+/// Atomically replace the file at `path`, or the one it links to, with
+/// `content`, keeping its permissions
+fn replace_file(path: &Path, content: &str) -> Result<()> {
+    let target = match fs::canonicalize(path) {
+        Ok(target) => target,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => path.to_path_buf(),
+        Err(e) => return Err(e).context("Failed to resolve the file"),
+    };
+    let permissions = match fs::metadata(&target) {
+        Ok(metadata) => Some(metadata.permissions()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e).context("Failed to read the file's permissions"),
+    };
+
+    let mut temp_file =
+        NamedTempFile::new_in(parent_dir(&target)?).context("Failed to create temporary file")?;
+    temp_file
+        .write_all(content.as_bytes())
+        .context("Failed to write to temporary file")?;
+    if let Some(permissions) = permissions {
+        temp_file
+            .as_file()
+            .set_permissions(permissions)
+            .context("Failed to copy the file's permissions")?;
+    }
+    temp_file
+        .persist(&target)
+        .context("Failed to persist temporary file")?;
+
+    debug!("Atomically replaced file: {:?}", target);
+    Ok(())
+}
+
+/// Directory holding `path`, `.` for a bare file name
+fn parent_dir(path: &Path) -> Result<&Path> {
+    match path.parent() {
+        Some(parent) if parent.as_os_str().is_empty() => Ok(Path::new(".")),
+        Some(parent) => Ok(parent),
+        None => bail!("Path has no parent directory"),
+    }
+}
+
 /// Atomically write content to a file using a temporary file
 pub fn atomic_write(path: &Path, content: &str) -> Result<()> {
-    let parent = path
-        .parent()
-        .ok_or_else(|| anyhow::anyhow!("Path has no parent directory"))?;
+    let parent = parent_dir(path)?;
 
     // Create temporary file in the same directory for atomic rename
     let mut temp_file = NamedTempFile::new_in(parent).context("Failed to create temporary file")?;
@@ -186,12 +457,340 @@ pub fn atomic_write(path: &Path, content: &str) -> Result<()> {
     Ok(())
 }
 
-/// Ensure goose config files exist with proper locking and atomic writes
-pub fn ensure_goose_config_files() -> Result<()> {
+/// Default goose config.yaml pointing at the local clad proxy
+pub const DEFAULT_CONFIG_YAML: &str = r#"OLLAMA_HOST: 127.0.0.1:8080
+GOOSE_MODEL: default-model
+GOOSE_PROVIDER: ollama
+extensions:
+  memory:
+    enabled: true
+    type: builtin
+    name: memory
+    display_name: Memory
+    description: null
+    timeout: 300
+    bundled: true
+    available_tools: []
+"#;
+
+/// Environment variable naming a file to use instead of `DEFAULT_CONFIG_YAML`
+pub const CONFIG_TEMPLATE_ENV: &str = "CLA_CONFIG_TEMPLATE";
+
+/// Content for a newly created config.yaml
+///
+/// Uses the file named by `CLA_CONFIG_TEMPLATE` when set, otherwise the
+/// built-in template.
+pub fn config_yaml_template() -> Result<String> {
+    match env::var(CONFIG_TEMPLATE_ENV) {
+        Ok(path) if !path.is_empty() => {
+            debug!(
+                "Using config template from {}: {:?}",
+                CONFIG_TEMPLATE_ENV, path
+            );
+            fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read config template {:?}", path))
+        }
+        _ => Ok(DEFAULT_CONFIG_YAML.to_string()),
+    }
+}
+
+/// Read a top-level `KEY: value` entry from config.yaml
+pub fn config_value<'a>(content: &'a str, key: &str) -> Option<&'a str> {
+    content.lines().find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        (name == key)
+            .then(|| value.trim())
+            .filter(|v| !v.is_empty())
+    })
+}
+
+/// Longest the backend preflight may delay the command
+pub const BACKEND_CHECK_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Port goose assumes when OLLAMA_HOST has none
+const OLLAMA_DEFAULT_PORT: u16 = 11434;
+
+/// The `host:port` goose will connect to, from config.yaml's OLLAMA_HOST
+pub fn backend_address(config_yaml: &str) -> Option<String> {
+    let host = config_value(config_yaml, "OLLAMA_HOST")?;
+    let host = host.trim_matches(|c| c == '"' || c == '\'');
+    let host = host.split_once("://").map_or(host, |(_, rest)| rest);
+    let host = host.split('/').next().unwrap_or_default();
+    if host.is_empty() {
+        return None;
+    }
+
+    let has_port = host
+        .rsplit_once(':')
+        .is_some_and(|(_, port)| port.parse::<u16>().is_ok());
+    Some(if has_port {
+        host.to_string()
+    } else {
+        format!("{}:{}", host, OLLAMA_DEFAULT_PORT)
+    })
+}
+
+/// Whether config.yaml's OLLAMA_HOST asks for HTTPS
+pub fn backend_uses_tls(config_yaml: &str) -> bool {
+    config_value(config_yaml, "OLLAMA_HOST").is_some_and(|host| {
+        host.trim_matches(|c| c == '"' || c == '\'')
+            .get(..8)
+            .is_some_and(|scheme| scheme.eq_ignore_ascii_case("https://"))
+    })
+}
+
+/// Whether something accepts TCP connections on `address` within `timeout`
+pub fn backend_is_reachable(address: &str, timeout: Duration) -> bool {
+    connect_before(address, Instant::now() + timeout).is_some()
+}
+
+/// Connect to `address`, giving up at `deadline`
+///
+/// Literal IP addresses are used as they are. Host names are resolved on
+/// their own thread, so a resolver that hangs can't hold the command past
+/// the deadline, and each resolved address gets the time left.
+fn connect_before(address: &str, deadline: Instant) -> Option<TcpStream> {
+    let addrs = match address.parse::<SocketAddr>() {
+        Ok(addr) => vec![addr],
+        Err(_) => resolve_before(address, deadline)?,
+    };
+
+    addrs.into_iter().find_map(|addr| {
+        let left = time_left(deadline)?;
+        TcpStream::connect_timeout(&addr, left).ok()
+    })
+}
+
+/// Time until `deadline`, `None` once it has passed
+fn time_left(deadline: Instant) -> Option<Duration> {
+    deadline
+        .checked_duration_since(Instant::now())
+        .filter(|left| !left.is_zero())
+}
+
+/// Resolve the host name in `address`, giving up at `deadline`
+fn resolve_before(address: &str, deadline: Instant) -> Option<Vec<SocketAddr>> {
+    let (sender, receiver) = mpsc::channel();
+    let host = address.to_string();
+    thread::spawn(move || {
+        let _ = sender.send(host.to_socket_addrs().map(Vec::from_iter));
+    });
+
+    match receiver.recv_timeout(time_left(deadline)?) {
+        Ok(Ok(addrs)) => Some(addrs),
+        Ok(Err(e)) => {
+            debug!("Failed to resolve backend address {}: {}", address, e);
+            None
+        }
+        Err(_) => {
+            debug!("Timed out resolving backend address {}", address);
+            None
+        }
+    }
+}
+
+/// Path requested by the rate limit pre-check, a route clad rate limits
+const PRECHECK_PATH: &str = "/v1/chat/completions";
+
+/// Most response bytes read by the pre-check, enough for the headers
+const PRECHECK_MAX_RESPONSE: u64 = 8192;
+
+/// Outcome of the `--precheck` request to the backend
+#[derive(Debug, PartialEq, Eq)]
+pub enum Precheck {
+    /// The backend answered without rate limiting
+    Ok,
+    /// The backend answered 429, with the `Retry-After` seconds if given
+    RateLimited(Option<u64>),
+    /// No HTTP answer within the timeout
+    Unreachable,
+}
+
+/// Send a HEAD request to the backend at `address`, over HTTPS with `tls`,
+/// to find out whether it is rate limiting requests
+///
+/// clad answers HEAD requests from its rate limit without counting them.
+pub fn precheck_backend(address: &str, tls: bool, timeout: Duration) -> Precheck {
+    let deadline = Instant::now() + timeout;
+    let response = connect_before(address, deadline).and_then(|stream| {
+        let left = time_left(deadline)?;
+        stream.set_read_timeout(Some(left)).ok()?;
+        stream.set_write_timeout(Some(left)).ok()?;
+        if !tls {
+            return send_head_request(stream, address);
+        }
+        let connector = native_tls::TlsConnector::new()
+            .map_err(|e| debug!("Failed to set up TLS: {}", e))
+            .ok()?;
+        let stream = connector
+            .connect(host_name(address), stream)
+            .map_err(|e| debug!("TLS handshake with {} failed: {}", address, e))
+            .ok()?;
+        send_head_request(stream, address)
+    });
+
+    match response.as_deref().and_then(parse_precheck_response) {
+        Some((429, retry_after)) => Precheck::RateLimited(retry_after),
+        Some(_) => Precheck::Ok,
+        None => {
+            debug!("No HTTP response from backend at {}", address);
+            Precheck::Unreachable
+        }
+    }
+}
+
+/// Send the pre-check request on `stream` and return the response headers
+fn send_head_request(mut stream: impl Read + Write, address: &str) -> Option<String> {
+    let request = format!(
+        "HEAD {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n",
+        PRECHECK_PATH, address
+    );
+    stream.write_all(request.as_bytes()).ok()?;
+    let mut response = Vec::new();
+    // A timeout after the headers arrived still leaves them readable
+    let _ = stream
+        .take(PRECHECK_MAX_RESPONSE)
+        .read_to_end(&mut response);
+    Some(String::from_utf8_lossy(&response).into_owned())
+}
+
+/// Host of a `host:port` address, as its certificate names it
+fn host_name(address: &str) -> &str {
+    let host = address.rsplit_once(':').map_or(address, |(host, _)| host);
+    host.trim_start_matches('[').trim_end_matches(']')
+}
+
+/// Status code and `Retry-After` seconds of a raw HTTP response
+fn parse_precheck_response(response: &str) -> Option<(u16, Option<u64>)> {
+    let mut lines = response.lines();
+    let status = lines
+        .next()?
+        .strip_prefix("HTTP/")?
+        .split_whitespace()
+        .nth(1)?
+        .parse()
+        .ok()?;
+    let retry_after = lines
+        .take_while(|line| !line.is_empty())
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("retry-after"))
+        .and_then(|(_, value)| value.trim().parse().ok());
+    Some((status, retry_after))
+}
+
+/// Resolve the goose config directory
+pub fn goose_config_dir() -> Result<PathBuf> {
     let home_dir = choose_app_strategy(GOOSE_APP_STRATEGY.clone())
         .context("Failed to determine app strategy (HOME environment variable may not be set)")?;
 
-    let config_dir = home_dir.in_config_dir("");
+    Ok(home_dir.in_config_dir(""))
+}
+
+/// Environment variable setting how many seconds to wait for the config lock
+pub const CONFIG_LOCK_TIMEOUT_ENV: &str = "CLA_CONFIG_LOCK_TIMEOUT";
+
+/// How long to wait for the config lock unless `CLA_CONFIG_LOCK_TIMEOUT` is set
+const DEFAULT_CONFIG_LOCK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long to wait for the config lock before saying so
+const CONFIG_LOCK_NOTICE_DELAY: Duration = Duration::from_secs(1);
+
+/// Pause between attempts to take the config lock
+const CONFIG_LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(50);
+
+/// The config lock stayed held by another process for the whole timeout
+#[derive(Debug)]
+pub struct ConfigLockTimeout {
+    /// The lock file
+    pub path: PathBuf,
+    /// How long we waited
+    pub timeout: Duration,
+}
+
+impl fmt::Display for ConfigLockTimeout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "gave up after {}s waiting for the config lock {:?} held by another process \
+             (set {} to wait longer)",
+            self.timeout.as_secs_f64(),
+            self.path,
+            CONFIG_LOCK_TIMEOUT_ENV
+        )
+    }
+}
+
+impl std::error::Error for ConfigLockTimeout {}
+
+/// How long to wait for the config lock, from `CLA_CONFIG_LOCK_TIMEOUT`
+///
+/// An unset or invalid value uses the 30 second default.
+fn config_lock_timeout() -> Duration {
+    let Ok(value) = env::var(CONFIG_LOCK_TIMEOUT_ENV) else {
+        return DEFAULT_CONFIG_LOCK_TIMEOUT;
+    };
+    match value
+        .parse::<f64>()
+        .ok()
+        .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+    {
+        Some(timeout) => timeout,
+        None => {
+            warn!(
+                "Invalid {} {:?}, using {}s",
+                CONFIG_LOCK_TIMEOUT_ENV,
+                value,
+                DEFAULT_CONFIG_LOCK_TIMEOUT.as_secs()
+            );
+            DEFAULT_CONFIG_LOCK_TIMEOUT
+        }
+    }
+}
+
+/// Take the exclusive lock on `lock_file`, waiting at most `timeout`
+///
+/// A notice is printed when another process holds the lock for more than a
+/// second, and [`ConfigLockTimeout`] is returned once `timeout` has passed.
+fn lock_within(lock_file: &File, path: &Path, timeout: Duration) -> Result<()> {
+    let start = Instant::now();
+    let mut notified = false;
+    loop {
+        match lock_file.try_lock_exclusive() {
+            Ok(()) => return Ok(()),
+            Err(e) if e.raw_os_error() != fs2::lock_contended_error().raw_os_error() => {
+                return Err(e).context("Failed to acquire lock on config directory");
+            }
+            Err(_) => {}
+        }
+
+        let waited = start.elapsed();
+        if waited >= timeout {
+            return Err(ConfigLockTimeout {
+                path: path.to_path_buf(),
+                timeout,
+            }
+            .into());
+        }
+        if !notified && waited >= CONFIG_LOCK_NOTICE_DELAY {
+            advise("Waiting for the config lock held by another process...");
+            notified = true;
+        }
+        thread::sleep(CONFIG_LOCK_RETRY_INTERVAL.min(timeout - waited));
+    }
+}
+
+/// Ensure goose config files exist with proper locking and atomic writes
+pub fn ensure_goose_config_files() -> Result<()> {
+    write_goose_config_files(&goose_config_dir()?, &config_yaml_template()?, false)?;
+    Ok(())
+}
+
+/// Write the goose config files into `config_dir`, using `config_yaml` as
+/// the content of config.yaml
+///
+/// An existing config.yaml is only replaced when `force` is set. Returns
+/// whether config.yaml was written.
+pub fn write_goose_config_files(config_dir: &Path, config_yaml: &str, force: bool) -> Result<bool> {
     let custom_providers_dir = config_dir.join("custom_providers");
 
     // Ensure directories exist
@@ -204,49 +803,42 @@ pub fn ensure_goose_config_files() -> Result<()> {
     let lock_file_path = config_dir.join(".config.lock");
     let lock_file = fs::OpenOptions::new()
         .create(true)
+        .truncate(false)
         .write(true)
         .open(&lock_file_path)
         .context("Failed to create lock file")?;
 
-    // Acquire exclusive lock (blocks if another process has the lock)
+    // Acquire exclusive lock, waiting a bounded time for another process
     debug!("Acquiring lock on {:?}", lock_file_path);
-    lock_file
-        .lock_exclusive()
-        .context("Failed to acquire lock on config directory")?;
+    lock_within(&lock_file, &lock_file_path, config_lock_timeout())?;
 
     // Check and create config.yaml
     let config_yaml_path = config_dir.join("config.yaml");
-    if !config_yaml_path.exists() {
+    let written = if force || !config_yaml_path.exists() {
         info!("Creating config.yaml at {:?}", config_yaml_path);
-
-        let config_yaml_content = r#"OLLAMA_HOST: 127.0.0.1:8080
-GOOSE_MODEL: default-model
-GOOSE_PROVIDER: ollama
-extensions:
-  memory:
-    enabled: true
-    type: builtin
-    name: memory
-    display_name: Memory
-    description: null
-    timeout: 300
-    bundled: true
-    available_tools: []
-"#;
-        atomic_write(&config_yaml_path, config_yaml_content)
-            .context("Failed to write config.yaml")?;
+        atomic_write(&config_yaml_path, config_yaml).context("Failed to write config.yaml")?;
+        true
     } else {
         debug!("config.yaml already exists");
-    }
+        false
+    };
 
     // Release lock (happens automatically when lock_file is dropped)
     FileExt::unlock(&lock_file).context("Failed to release lock")?;
 
     debug!("Config files ensured successfully");
-    Ok(())
+    Ok(written)
 }
 
+/// Signal sent to goose when the reader of its output goes away
+#[cfg(unix)]
+const SIGPIPE: i32 = 13;
+
 /// Convert exit status to exit code, handling both normal exit and signals
+///
+/// A goose killed by SIGPIPE only means the output was piped into a reader
+/// that stopped early, as in `c ... | head -1`, so it counts as success like
+/// it would for other Unix tools.
 pub fn status_to_exit_code(status: std::process::ExitStatus) -> i32 {
     #[cfg(unix)]
     {
@@ -258,6 +850,11 @@ pub fn status_to_exit_code(status: std::process::ExitStatus) -> i32 {
             return code;
         }
 
+        if status.signal() == Some(SIGPIPE) {
+            debug!("Child stopped by SIGPIPE, output reader closed early");
+            return 0;
+        }
+
         if let Some(signal) = status.signal() {
             // Terminated by signal - return 128 + signal number (shell convention)
             let exit_code = 128 + signal;
@@ -272,6 +869,21 @@ pub fn status_to_exit_code(status: std::process::ExitStatus) -> i32 {
         warn!("Unknown exit status, returning 1");
         1
     }
+
+    #[cfg(windows)]
+    {
+        // Windows has no signals, every exit carries a code
+        match status.code() {
+            Some(code) => {
+                debug!("Child exited with code: {}", code);
+                code
+            }
+            None => {
+                warn!("Unknown exit status, returning 1");
+                1
+            }
+        }
+    }
 }
 
 /// Filter environment variables to only pass safe ones
@@ -294,6 +906,33 @@ pub fn get_filtered_env() -> Vec<(String, String)> {
         "PAGER",
         "DISPLAY",
         "COLORTERM",
+        // Networking, needed by goose (and clad) behind corporate proxies
+        "HTTP_PROXY",
+        "http_proxy",
+        "HTTPS_PROXY",
+        "https_proxy",
+        "NO_PROXY",
+        "no_proxy",
+        "REQUESTS_CA_BUNDLE",
+        "SSL_CERT_FILE",
+    ];
+
+    // Variables Windows programs need to locate profiles, temp dirs and DLLs.
+    // Names are case-insensitive there (PATH is usually spelled "Path").
+    #[cfg(windows)]
+    const WINDOWS_ENV_VARS: &[&str] = &[
+        "USERPROFILE",
+        "USERNAME",
+        "APPDATA",
+        "LOCALAPPDATA",
+        "PROGRAMDATA",
+        "SYSTEMROOT",
+        "SYSTEMDRIVE",
+        "WINDIR",
+        "COMSPEC",
+        "PATHEXT",
+        "TEMP",
+        "TMP",
     ];
 
     // Additional patterns to allow (for development)
@@ -306,6 +945,15 @@ pub fn get_filtered_env() -> Vec<(String, String)> {
                 return true;
             }
 
+            #[cfg(windows)]
+            if SAFE_ENV_VARS
+                .iter()
+                .chain(WINDOWS_ENV_VARS)
+                .any(|safe| key.eq_ignore_ascii_case(safe))
+            {
+                return true;
+            }
+
             // Allow safe prefixes
             for prefix in SAFE_PREFIXES {
                 if key.starts_with(prefix) {
@@ -381,6 +1029,33 @@ mod tests {
         );
     }
 
+    #[test]
+    #[cfg(windows)]
+    fn test_is_executable_with_unknown_extension() {
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("test_file.txt");
+
+        fs::write(&file_path, "test").unwrap();
+
+        assert!(
+            !is_executable(&file_path),
+            "File without an executable extension should return false"
+        );
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn test_is_executable_with_executable_file() {
+        let temp_dir = TempDir::new().unwrap();
+
+        for name in ["goose.exe", "goose.CMD", "goose.bat"] {
+            let file_path = temp_dir.path().join(name);
+            fs::write(&file_path, "test").unwrap();
+
+            assert!(is_executable(&file_path), "{} should be executable", name);
+        }
+    }
+
     // ============================================================================
     // Tests for find_goose
     // ============================================================================
@@ -449,6 +1124,30 @@ mod tests {
         }
     }
 
+    #[test]
+    #[cfg(windows)]
+    #[allow(unsafe_code)]
+    fn test_find_goose_with_valid_env_var() {
+        let temp_dir = TempDir::new().unwrap();
+        let goose_path = temp_dir.path().join(GOOSE_EXECUTABLE);
+
+        // Create a mock executable
+        fs::write(&goose_path, "test").unwrap();
+
+        unsafe {
+            // Set environment variable
+            env::set_var("GOOSE_BINARY", goose_path.to_str().unwrap());
+
+            let result = find_goose();
+
+            // Clean up
+            env::remove_var("GOOSE_BINARY");
+
+            assert!(result.is_ok(), "Should find goose from env var");
+            assert_eq!(result.unwrap(), goose_path);
+        }
+    }
+
     // ============================================================================
     // Tests for is_goose_subcommand
     // ============================================================================
@@ -568,6 +1267,55 @@ mod tests {
         assert_eq!(read_content, content);
     }
 
+    #[test]
+    fn test_save_answer_replaces_or_appends() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("nginx.service");
+        check_output_path(&path).unwrap();
+
+        save_answer(&path, "[Unit]\n", true).unwrap();
+        save_answer(&path, "[Service]\n", true).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "[Unit]\n[Service]\n");
+
+        save_answer(&path, "[Install]\n", false).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "[Install]\n");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_save_answer_keeps_mode_and_symlinks() {
+        use std::os::unix::fs::{symlink, PermissionsExt};
+
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("nginx.conf");
+        fs::write(&path, "old\n").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        let link = temp_dir.path().join("current.conf");
+        symlink(&path, &link).unwrap();
+
+        save_answer(&path, "new\n", false).unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o644);
+
+        save_answer(&link, "newer\n", false).unwrap();
+        assert!(fs::symlink_metadata(&link)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "newer\n");
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o644);
+    }
+
+    #[test]
+    fn test_check_output_path_rejects_unusable_paths() {
+        let temp_dir = TempDir::new().unwrap();
+
+        assert!(check_output_path(temp_dir.path()).is_err());
+        assert!(check_output_path(&temp_dir.path().join("missing/answer.txt")).is_err());
+        assert_eq!(parent_dir(Path::new("answer.txt")).unwrap(), Path::new("."));
+    }
+
     #[test]
     fn test_atomic_write_overwrites_existing() {
         let temp_dir = TempDir::new().unwrap();
@@ -625,15 +1373,57 @@ mod tests {
         unsafe {
             env::set_var("RANDOM_VAR", "should not pass");
             env::set_var("MALICIOUS", "data");
+            env::set_var("FTP_PROXY", "http://proxy:3128");
+            env::set_var("HTTP_PROXY_PASSWORD", "secret");
 
             let filtered = get_filtered_env();
 
             assert!(!filtered.iter().any(|(k, _)| k == "RANDOM_VAR"));
             assert!(!filtered.iter().any(|(k, _)| k == "MALICIOUS"));
+            assert!(!filtered.iter().any(|(k, _)| k == "FTP_PROXY"));
+            assert!(!filtered.iter().any(|(k, _)| k == "HTTP_PROXY_PASSWORD"));
 
             // Clean up
             env::remove_var("RANDOM_VAR");
             env::remove_var("MALICIOUS");
+            env::remove_var("FTP_PROXY");
+            env::remove_var("HTTP_PROXY_PASSWORD");
+        }
+    }
+
+    #[test]
+    #[allow(unsafe_code)]
+    fn test_get_filtered_env_includes_network_vars() {
+        let network_vars = [
+            "HTTP_PROXY",
+            "http_proxy",
+            "HTTPS_PROXY",
+            "https_proxy",
+            "NO_PROXY",
+            "no_proxy",
+            "REQUESTS_CA_BUNDLE",
+            "SSL_CERT_FILE",
+        ];
+
+        unsafe {
+            for var in network_vars {
+                env::set_var(var, "value");
+            }
+
+            let filtered = get_filtered_env();
+
+            for var in network_vars {
+                assert!(
+                    filtered.iter().any(|(k, v)| k == var && v == "value"),
+                    "{} should be forwarded",
+                    var
+                );
+            }
+
+            // Clean up
+            for var in network_vars {
+                env::remove_var(var);
+            }
         }
     }
 
@@ -688,6 +1478,28 @@ mod tests {
         assert_eq!(code, 128 + 2);
     }
 
+    #[test]
+    #[cfg(windows)]
+    fn test_status_to_exit_code_normal() {
+        use std::os::windows::process::ExitStatusExt;
+
+        let status = std::process::ExitStatus::from_raw(0);
+        assert_eq!(status_to_exit_code(status), 0);
+
+        let status = std::process::ExitStatus::from_raw(1);
+        assert_eq!(status_to_exit_code(status), 1);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_status_to_exit_code_broken_pipe_is_success() {
+        use std::os::unix::process::ExitStatusExt;
+
+        // Signal 13 (SIGPIPE), e.g. `c ... | head -1`
+        let status = std::process::ExitStatus::from_raw(SIGPIPE);
+        assert_eq!(status_to_exit_code(status), 0);
+    }
+
     // ============================================================================
     // Integration tests for ensure_goose_config_files
     // ============================================================================
@@ -708,4 +1520,377 @@ mod tests {
         // Should either succeed or fail gracefully
         let _ = result;
     }
+
+    #[test]
+    fn test_write_goose_config_files_creates_default() {
+        let temp_dir = TempDir::new().unwrap();
+
+        let written =
+            write_goose_config_files(temp_dir.path(), DEFAULT_CONFIG_YAML, false).unwrap();
+
+        assert!(written);
+        assert!(temp_dir.path().join("custom_providers").is_dir());
+        assert_eq!(
+            fs::read_to_string(temp_dir.path().join("config.yaml")).unwrap(),
+            DEFAULT_CONFIG_YAML
+        );
+    }
+
+    #[test]
+    fn test_write_goose_config_files_keeps_existing_without_force() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_yaml = temp_dir.path().join("config.yaml");
+        fs::write(&config_yaml, "GOOSE_MODEL: custom\n").unwrap();
+
+        assert!(!write_goose_config_files(temp_dir.path(), DEFAULT_CONFIG_YAML, false).unwrap());
+        assert_eq!(
+            fs::read_to_string(&config_yaml).unwrap(),
+            "GOOSE_MODEL: custom\n"
+        );
+
+        assert!(write_goose_config_files(temp_dir.path(), DEFAULT_CONFIG_YAML, true).unwrap());
+        assert_eq!(
+            fs::read_to_string(&config_yaml).unwrap(),
+            DEFAULT_CONFIG_YAML
+        );
+    }
+
+    #[test]
+    fn test_lock_within_gives_up_while_held() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(".config.lock");
+        let open = || {
+            fs::OpenOptions::new()
+                .create(true)
+                .truncate(false)
+                .write(true)
+                .open(&path)
+                .unwrap()
+        };
+        let holder = open();
+        holder.lock_exclusive().unwrap();
+        let waiter = open();
+
+        let err = lock_within(&waiter, &path, Duration::from_millis(100)).unwrap_err();
+        let timeout = err.downcast_ref::<ConfigLockTimeout>().unwrap();
+        assert_eq!(timeout.timeout, Duration::from_millis(100));
+        assert!(err.to_string().contains(CONFIG_LOCK_TIMEOUT_ENV), "{}", err);
+
+        FileExt::unlock(&holder).unwrap();
+        lock_within(&waiter, &path, Duration::from_millis(100)).unwrap();
+    }
+
+    #[test]
+    #[allow(unsafe_code)]
+    fn test_config_yaml_template_from_env() {
+        let temp_dir = TempDir::new().unwrap();
+        let template = temp_dir.path().join("template.yaml");
+        fs::write(
+            &template,
+            "OLLAMA_HOST: 10.0.0.5:9090\nGOOSE_MODEL: fleet-model\n",
+        )
+        .unwrap();
+
+        unsafe {
+            env::set_var(CONFIG_TEMPLATE_ENV, &template);
+            let custom = config_yaml_template();
+            env::set_var(CONFIG_TEMPLATE_ENV, temp_dir.path().join("missing.yaml"));
+            let missing = config_yaml_template();
+            env::remove_var(CONFIG_TEMPLATE_ENV);
+            let default = config_yaml_template();
+
+            assert_eq!(
+                custom.unwrap(),
+                "OLLAMA_HOST: 10.0.0.5:9090\nGOOSE_MODEL: fleet-model\n"
+            );
+            assert!(missing.is_err());
+            assert_eq!(default.unwrap(), DEFAULT_CONFIG_YAML);
+        }
+    }
+
+    // ============================================================================
+    // Tests for validate_session_name
+    // ============================================================================
+
+    #[test]
+    fn test_validate_session_name_accepts_simple_names() {
+        assert!(validate_session_name("foo").is_ok());
+        assert!(validate_session_name("httpd-debug_2").is_ok());
+        assert!(validate_session_name(&"a".repeat(MAX_SESSION_NAME_LENGTH)).is_ok());
+    }
+
+    #[test]
+    fn test_validate_session_name_rejects_bad_names() {
+        for name in [
+            "",
+            "a/b",
+            "..\\x",
+            "..",
+            "bad\0name",
+            "--force",
+            &"a".repeat(MAX_SESSION_NAME_LENGTH + 1),
+        ] {
+            assert!(
+                validate_session_name(name).is_err(),
+                "Expected {:?} to be rejected",
+                name
+            );
+        }
+    }
+
+    // ============================================================================
+    // Tests for read_attachment
+    // ============================================================================
+
+    #[test]
+    fn test_read_attachment_text() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("main.rs");
+        fs::write(&path, "fn main() {}\n").unwrap();
+
+        assert_eq!(read_attachment(&path, 100).unwrap(), "fn main() {}\n");
+    }
+
+    #[test]
+    fn test_read_attachment_refuses_binary_and_non_utf8() {
+        let temp_dir = TempDir::new().unwrap();
+        let binary = temp_dir.path().join("a.out");
+        fs::write(&binary, b"\x7fELF\x00\x01").unwrap();
+        let latin1 = temp_dir.path().join("latin1.txt");
+        fs::write(&latin1, b"caf\xe9").unwrap();
+
+        let err = read_attachment(&binary, 100).unwrap_err().to_string();
+        assert!(err.contains("is a binary file"), "{}", err);
+        let err = read_attachment(&latin1, 100).unwrap_err().to_string();
+        assert!(err.contains("is not UTF-8 text"), "{}", err);
+    }
+
+    #[test]
+    fn test_read_attachment_limit() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("big.txt");
+        fs::write(&path, "a".repeat(101)).unwrap();
+
+        assert!(read_attachment(&path, 101).is_ok());
+        let err = read_attachment(&path, 100).unwrap_err().to_string();
+        assert!(err.contains("too large to attach"), "{}", err);
+    }
+
+    #[test]
+    fn test_read_attachment_missing_or_directory() {
+        let temp_dir = TempDir::new().unwrap();
+
+        assert!(read_attachment(&temp_dir.path().join("missing"), 100).is_err());
+        let err = read_attachment(temp_dir.path(), 100)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("not a regular file"), "{}", err);
+    }
+
+    // ============================================================================
+    // Tests for read_from_editor
+    // ============================================================================
+
+    #[cfg(unix)]
+    fn editor_script(temp_dir: &TempDir, body: &str) -> String {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = temp_dir.path().join("editor");
+        fs::write(&path, format!("#!/bin/sh\n{}\n", body)).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        path.display().to_string()
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_read_from_editor_returns_saved_text() {
+        let temp_dir = TempDir::new().unwrap();
+        let editor = editor_script(&temp_dir, r#"printf 'line one\nline two\n' > "$1""#);
+
+        assert_eq!(
+            read_from_editor(&editor, "").unwrap(),
+            "line one\nline two\n"
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_read_from_editor_starts_from_initial_text() {
+        let temp_dir = TempDir::new().unwrap();
+        let editor = editor_script(&temp_dir, r#"printf ' on RHEL 9' >> "$1""#);
+
+        assert_eq!(
+            read_from_editor(&editor, "how do I enable a service").unwrap(),
+            "how do I enable a service on RHEL 9"
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_read_from_editor_passes_editor_arguments() {
+        let temp_dir = TempDir::new().unwrap();
+        let editor = editor_script(&temp_dir, r#"printf '%s' "$1" > "$2""#);
+
+        assert_eq!(
+            read_from_editor(&format!("{} --wait", editor), "").unwrap(),
+            "--wait"
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_read_from_editor_fails_with_editor() {
+        let temp_dir = TempDir::new().unwrap();
+        let editor = editor_script(&temp_dir, "exit 1");
+
+        let err = read_from_editor(&editor, "").unwrap_err().to_string();
+        assert!(err.contains("exited with"), "{}", err);
+        assert!(read_from_editor("/nonexistent/editor", "").is_err());
+        assert!(read_from_editor("  ", "").is_err());
+    }
+
+    // ============================================================================
+    // Tests for the backend preflight
+    // ============================================================================
+
+    #[test]
+    fn test_config_value_reads_top_level_keys() {
+        let content = "GOOSE_MODEL: default-model\nextensions:\n  GOOSE_PROVIDER: nested\n";
+
+        assert_eq!(config_value(content, "GOOSE_MODEL"), Some("default-model"));
+        assert_eq!(config_value(content, "GOOSE_PROVIDER"), None);
+        assert_eq!(config_value(content, "extensions"), None);
+    }
+
+    #[test]
+    fn test_backend_address_from_config() {
+        assert_eq!(
+            backend_address(DEFAULT_CONFIG_YAML),
+            Some("127.0.0.1:8080".to_string())
+        );
+        assert_eq!(
+            backend_address("OLLAMA_HOST: http://proxy.example.com:9000/\n"),
+            Some("proxy.example.com:9000".to_string())
+        );
+        assert_eq!(
+            backend_address("OLLAMA_HOST: localhost\n"),
+            Some("localhost:11434".to_string())
+        );
+        assert_eq!(backend_address("GOOSE_MODEL: default-model\n"), None);
+    }
+
+    #[test]
+    fn test_backend_is_reachable() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+
+        assert!(backend_is_reachable(&address, BACKEND_CHECK_TIMEOUT));
+
+        drop(listener);
+        assert!(!backend_is_reachable(&address, BACKEND_CHECK_TIMEOUT));
+    }
+
+    #[test]
+    fn test_connect_before_keeps_one_deadline() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+
+        // A literal address is used without resolving it
+        assert!(connect_before(&address, Instant::now() + BACKEND_CHECK_TIMEOUT).is_some());
+        // Nothing is tried once the deadline has passed
+        assert!(connect_before(&address, Instant::now()).is_none());
+        assert!(connect_before("localhost:1", Instant::now()).is_none());
+    }
+
+    #[test]
+    fn test_parse_precheck_response() {
+        assert_eq!(
+            parse_precheck_response("HTTP/1.1 429 Too Many Requests\r\nretry-after: 3\r\n\r\n"),
+            Some((429, Some(3)))
+        );
+        assert_eq!(
+            parse_precheck_response("HTTP/1.1 429 Too Many Requests\r\nRetry-After: soon\r\n\r\n"),
+            Some((429, None))
+        );
+        assert_eq!(
+            parse_precheck_response("HTTP/1.1 405 Method Not Allowed\r\n\r\nretry-after: 3"),
+            Some((405, None))
+        );
+        assert_eq!(parse_precheck_response(""), None);
+        assert_eq!(parse_precheck_response("SSH-2.0-OpenSSH_9.6\r\n"), None);
+    }
+
+    #[test]
+    fn test_precheck_backend_reads_retry_after() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0u8; 1024];
+            while !request.ends_with(b"\r\n\r\n") {
+                let n = stream.read(&mut buf).unwrap();
+                assert!(n > 0, "connection closed before the request ended");
+                request.extend_from_slice(&buf[..n]);
+            }
+            stream
+                .write_all(b"HTTP/1.1 429 Too Many Requests\r\nretry-after: 7\r\ncontent-length: 0\r\n\r\n")
+                .unwrap();
+            String::from_utf8_lossy(&request).into_owned()
+        });
+
+        let result = precheck_backend(&address, false, Duration::from_secs(5));
+        let request = server.join().unwrap();
+
+        assert_eq!(result, Precheck::RateLimited(Some(7)));
+        assert!(request.starts_with("HEAD /v1/chat/completions HTTP/1.1\r\n"));
+    }
+
+    #[test]
+    fn test_precheck_backend_unreachable() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        drop(listener);
+
+        assert_eq!(
+            precheck_backend(&address, false, BACKEND_CHECK_TIMEOUT),
+            Precheck::Unreachable
+        );
+    }
+
+    #[test]
+    fn test_precheck_backend_uses_tls() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut first = [0u8; 1];
+            stream.read_exact(&mut first).unwrap();
+            first[0]
+        });
+
+        // No certificate is offered, so the handshake never completes
+        let result = precheck_backend(&address, true, BACKEND_CHECK_TIMEOUT);
+        let first = server.join().unwrap();
+
+        assert_eq!(result, Precheck::Unreachable);
+        // A TLS handshake record, not "HEAD"
+        assert_eq!(first, 0x16);
+    }
+
+    #[test]
+    fn test_backend_uses_tls() {
+        assert!(backend_uses_tls("OLLAMA_HOST: https://clad.example:8443\n"));
+        assert!(backend_uses_tls("OLLAMA_HOST: \"HTTPS://clad.example\"\n"));
+        assert!(!backend_uses_tls("OLLAMA_HOST: http://localhost:8000\n"));
+        assert!(!backend_uses_tls("OLLAMA_HOST: localhost:8000\n"));
+        assert!(!backend_uses_tls("GOOSE_MODEL: default-model\n"));
+    }
+
+    #[test]
+    fn test_host_name() {
+        assert_eq!(host_name("clad.example:8443"), "clad.example");
+        assert_eq!(host_name("[::1]:8443"), "::1");
+        assert_eq!(host_name("clad.example"), "clad.example");
+    }
 }
@@ -6,10 +6,19 @@
 //! - c chat "query" → Explicit chat command
 //! - c history → View chat history
 //! - c shell → Shell integration features
+//! - c config → Show and manage configuration
 
+mod color;
 mod commands;
 mod config;
+mod context;
+mod error;
 mod helpers;
+mod markdown;
+mod output;
+mod safety;
+mod session;
+mod steps;
 
 #[cfg(feature = "docgen")]
 mod cli_json;
@@ -19,6 +28,7 @@ use log::info;
 use std::process::exit;
 
 use crate::commands::chat::ChatArgs;
+use crate::commands::config::{ConfigArgs, CONFIG_SUBCOMMANDS};
 use crate::commands::history::HistoryArgs;
 use crate::commands::shell::ShellArgs;
 
@@ -35,6 +45,18 @@ pub struct Cli {
     /// Subcommand to execute (defaults to chat if not specified)
     #[command(subcommand)]
     pub command: Option<Commands>,
+
+    /// Disable colored output (also disabled by NO_COLOR or when stdout is not a terminal)
+    #[arg(long, global = true)]
+    pub no_color: bool,
+
+    /// Suppress advisory messages on stderr; errors and exit codes are unchanged
+    #[arg(short, long, global = true)]
+    pub quiet: bool,
+
+    /// Show more log output: -v for info, -vv for debug (RUST_LOG takes precedence)
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    pub verbose: u8,
 }
 
 /// Available subcommands for the CLI
@@ -49,6 +71,9 @@ pub enum Commands {
     /// Shell integration and features
     Shell(ShellArgs),
 
+    /// Show and manage configuration
+    Config(ConfigArgs),
+
     /// Internal commands for tooling (not for end users)
     #[command(hide = true)]
     Internals {
@@ -67,79 +92,144 @@ pub enum InternalsCommands {
 
 impl Cli {
     /// Execute the CLI command - dispatches to appropriate subcommand
-    pub fn execute(self) {
+    ///
+    /// Returns the exit code; a failed command is reported on stderr first.
+    pub fn execute(self) -> i32 {
+        color::init(self.no_color);
+        output::init(self.quiet);
+
         // Handle internal commands first (for doc generation)
         if let Some(Commands::Internals { command }) = &self.command {
-            self.execute_internals(command);
-            return;
+            return self.execute_internals(command);
         }
 
         // Dispatch to subcommand
-        match self.command {
+        let result = match self.command {
             Some(Commands::Chat(args)) => args.execute(),
-            Some(Commands::History(args)) => args.execute(),
-            Some(Commands::Shell(args)) => args.execute(),
+            Some(Commands::History(args)) => {
+                args.execute();
+                Ok(0)
+            }
+            Some(Commands::Shell(args)) => {
+                args.execute();
+                Ok(0)
+            }
+            Some(Commands::Config(args)) => args.execute(),
             Some(Commands::Internals { .. }) => unreachable!("Already handled above"),
 
             // No subcommand specified - show help
             None => {
                 let _ = Cli::command().print_help();
                 eprintln!();
-                exit(1);
+                Ok(1)
             }
-        }
+        };
+
+        result.unwrap_or_else(|e| {
+            e.report();
+            e.exit_code()
+        })
     }
 
     /// Execute internal commands (for tooling/doc generation)
     #[cfg(feature = "docgen")]
-    fn execute_internals(&self, command: &InternalsCommands) {
+    fn execute_internals(&self, command: &InternalsCommands) -> i32 {
         match command {
             InternalsCommands::DumpCliJson => {
                 let cmd = Cli::command();
                 crate::cli_json::dump_cli_json(&cmd);
-                exit(0);
+                0
             }
         }
     }
 
     /// Execute internal commands (stub for when docgen is not enabled)
     #[cfg(not(feature = "docgen"))]
-    fn execute_internals(&self, _command: &InternalsCommands) {
+    fn execute_internals(&self, _command: &InternalsCommands) -> i32 {
         eprintln!("Error: Internal commands require the 'docgen' feature to be enabled");
-        exit(1);
+        1
     }
 }
 
 fn main() {
-    // Initialize logging - responds to RUST_LOG environment variable
-    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn"))
-        .format_timestamp(None)
-        .init();
-
-    info!("Command Line Assistant CLI starting");
-
     // Get raw arguments
     let args: Vec<String> = std::env::args().collect();
 
     // Check if we should route to default chat subcommand
     let should_default_to_chat = should_route_to_chat(&args);
 
-    // Parse command-line arguments and execute
-    if should_default_to_chat {
+    // Parse command-line arguments
+    let cli = if should_default_to_chat {
         // Prepend "chat" to route to chat subcommand
         let mut new_args = vec![args[0].clone(), "chat".to_string()];
         new_args.extend_from_slice(&args[1..]);
 
         match Cli::try_parse_from(&new_args) {
-            Ok(cli) => cli.execute(),
+            Ok(cli) => cli,
             Err(e) => e.exit(),
         }
     } else {
-        let cli = Cli::parse();
-        cli.execute();
+        Cli::parse_from(history_full_alias(args))
+    };
+
+    // Initialize logging before anything runs - RUST_LOG wins over -v
+    env_logger::Builder::from_env(
+        env_logger::Env::default().default_filter_or(log_filter(cli.verbose)),
+    )
+    .format_timestamp(None)
+    .init();
+
+    info!("Command Line Assistant CLI starting");
+    exit(cli.execute());
+}
+
+/// Log filter for the number of `-v` flags, used when RUST_LOG is unset
+fn log_filter(verbose: u8) -> &'static str {
+    match verbose {
+        0 => "warn",
+        1 => "info",
+        _ => "debug",
     }
 }
 
+/// Whether `arg` is one of the global flags, which may appear anywhere
+fn is_global_flag(arg: &str) -> bool {
+    const GLOBAL_FLAGS: [&str; 4] = ["--no-color", "--quiet", "-q", "--verbose"];
+
+    GLOBAL_FLAGS.contains(&arg)
+        || arg
+            .strip_prefix('-')
+            .is_some_and(|count| !count.is_empty() && count.chars().all(|c| c == 'v'))
+}
+
+/// Read `-v` after the history subcommand as `--full`
+///
+/// `c history -v` showed full details before `-v` became the global
+/// verbosity flag, so it keeps doing so as a hidden alias; `-v` before the
+/// subcommand still raises the log level.
+fn history_full_alias(args: Vec<String>) -> Vec<String> {
+    let Some(history) = args
+        .iter()
+        .skip(1)
+        .position(|arg| !is_global_flag(arg))
+        .map(|index| index + 1)
+        .filter(|&index| args[index] == "history")
+    else {
+        return args;
+    };
+
+    args.into_iter()
+        .enumerate()
+        .map(|(index, arg)| {
+            if index > history && arg == "-v" {
+                "--full".to_string()
+            } else {
+                arg
+            }
+        })
+        .collect()
+}
+
 /// Determine if the arguments should route to the chat subcommand
 ///
 /// This handles the default routing logic:
@@ -149,6 +239,9 @@ fn main() {
 /// - `c history from yesterday` -> chat mode (natural language query)
 /// - `c shell --install` -> shell subcommand (flag detected)
 /// - `c shell is broken` -> chat mode (natural language query)
+/// - `c config show` -> config subcommand
+/// - `c --no-color config show` -> config subcommand (global flags like --quiet are skipped)
+/// - `c config my network` -> chat mode (natural language query)
 /// - `c -i` -> chat mode
 /// - `c hello world` -> chat mode
 fn should_route_to_chat(args: &[String]) -> bool {
@@ -156,7 +249,17 @@ fn should_route_to_chat(args: &[String]) -> bool {
         return false;
     }
 
-    let first_arg = args[1].as_str();
+    // Global flags don't decide where the arguments go
+    let args: Vec<&str> = args
+        .iter()
+        .map(String::as_str)
+        .filter(|arg| !is_global_flag(arg))
+        .collect();
+    if args.len() <= 1 {
+        return false;
+    }
+
+    let first_arg = args[1];
     let help_version_flags = ["--help", "-h", "--version", "-V"];
 
     // If it's already the chat subcommand, don't route (already explicit)
@@ -169,6 +272,13 @@ fn should_route_to_chat(args: &[String]) -> bool {
         return false;
     }
 
+    // config takes its own subcommands, anything else after it is a query
+    if first_arg == "config" {
+        return args
+            .get(2)
+            .is_some_and(|arg| !arg.starts_with('-') && !CONFIG_SUBCOMMANDS.contains(arg));
+    }
+
     // For other known subcommands (history, shell), check if there are additional args
     let other_subcommands = ["history", "shell"];
     if other_subcommands.contains(&first_arg) {
@@ -200,6 +310,7 @@ fn should_route_to_chat(args: &[String]) -> bool {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::commands::chat::OutputFormat;
 
     /// Helper to convert string slices to Vec<String> for testing
     fn args_vec(args: &[&str]) -> Vec<String> {
@@ -353,6 +464,162 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_config_subcommands_go_to_subcommand() {
+        for args in [
+            &["c", "config"][..],
+            &["c", "config", "show"],
+            &["c", "config", "path"],
+            &["c", "config", "init", "--force"],
+            &["c", "config", "--help"],
+        ] {
+            assert!(
+                !should_route_to_chat(&args_vec(args)),
+                "Expected {:?} to go to config subcommand",
+                args
+            );
+        }
+    }
+
+    #[test]
+    fn test_config_with_query_routes_to_chat() {
+        let args = args_vec(&["c", "config", "my", "network"]);
+        assert!(should_route_to_chat(&args));
+    }
+
+    #[test]
+    fn test_no_color_flag_does_not_affect_routing() {
+        assert!(!should_route_to_chat(&args_vec(&[
+            "c",
+            "--no-color",
+            "config",
+            "show"
+        ])));
+        assert!(!should_route_to_chat(&args_vec(&["c", "--no-color"])));
+        assert!(should_route_to_chat(&args_vec(&[
+            "c",
+            "--no-color",
+            "list",
+            "files"
+        ])));
+    }
+
+    #[test]
+    fn test_parse_no_color_flag() {
+        let cli =
+            Cli::try_parse_from(&["c", "--no-color", "config", "show"]).expect("Failed to parse");
+        assert!(cli.no_color);
+
+        let cli =
+            Cli::try_parse_from(&["c", "config", "show", "--no-color"]).expect("Failed to parse");
+        assert!(cli.no_color);
+    }
+
+    #[test]
+    fn test_quiet_flag_does_not_affect_routing() {
+        assert!(!should_route_to_chat(&args_vec(&[
+            "c", "-q", "config", "path"
+        ])));
+        assert!(!should_route_to_chat(&args_vec(&[
+            "c", "--quiet", "history"
+        ])));
+        assert!(should_route_to_chat(&args_vec(&[
+            "c", "-q", "list", "files"
+        ])));
+    }
+
+    #[test]
+    fn test_verbose_flag_does_not_affect_routing() {
+        assert!(!should_route_to_chat(&args_vec(&[
+            "c", "-vv", "config", "path"
+        ])));
+        assert!(!should_route_to_chat(&args_vec(&[
+            "c",
+            "--verbose",
+            "history"
+        ])));
+        assert!(!should_route_to_chat(&args_vec(&["c", "-v"])));
+        assert!(should_route_to_chat(&args_vec(&[
+            "c", "-v", "list", "files"
+        ])));
+        // Other flags are still chat arguments
+        assert!(should_route_to_chat(&args_vec(&["c", "-i"])));
+    }
+
+    #[test]
+    fn test_parse_verbose_flag() {
+        let cli = Cli::try_parse_from(&["c", "chat", "list", "files"]).expect("Failed to parse");
+        assert_eq!(cli.verbose, 0);
+
+        let cli =
+            Cli::try_parse_from(&["c", "-v", "chat", "list", "files"]).expect("Failed to parse");
+        assert_eq!(cli.verbose, 1);
+
+        let cli = Cli::try_parse_from(&["c", "config", "show", "-vv"]).expect("Failed to parse");
+        assert_eq!(cli.verbose, 2);
+
+        let cli = Cli::try_parse_from(&["c", "history", "--verbose"]).expect("Failed to parse");
+        assert_eq!(cli.verbose, 1);
+    }
+
+    #[test]
+    fn test_history_v_still_shows_full_details() {
+        let parse = |args: &[&str]| {
+            let cli =
+                Cli::try_parse_from(history_full_alias(args_vec(args))).expect("Failed to parse");
+            match cli.command {
+                Some(Commands::History(history)) => (history.full, cli.verbose),
+                other => panic!("Expected History command, got {:?}", other),
+            }
+        };
+
+        assert_eq!(parse(&["c", "history", "-v"]), (true, 0));
+        assert_eq!(parse(&["c", "-v", "history", "-v"]), (true, 1));
+        assert_eq!(parse(&["c", "-v", "history"]), (false, 1));
+        assert_eq!(parse(&["c", "history", "--verbose"]), (false, 1));
+        assert_eq!(
+            history_full_alias(args_vec(&["c", "config", "show", "-v"])),
+            args_vec(&["c", "config", "show", "-v"])
+        );
+    }
+
+    #[test]
+    fn test_log_filter_for_verbosity() {
+        assert_eq!(log_filter(0), "warn");
+        assert_eq!(log_filter(1), "info");
+        assert_eq!(log_filter(2), "debug");
+        assert_eq!(log_filter(5), "debug");
+    }
+
+    #[test]
+    fn test_parse_quiet_flag() {
+        let cli =
+            Cli::try_parse_from(&["c", "chat", "-q", "list", "files"]).expect("Failed to parse");
+        assert!(cli.quiet);
+        match cli.command {
+            Some(Commands::Chat(args)) => assert_eq!(args.query, vec!["list", "files"]),
+            _ => panic!("Expected Chat command"),
+        }
+
+        let cli =
+            Cli::try_parse_from(&["c", "--quiet", "config", "show"]).expect("Failed to parse");
+        assert!(cli.quiet);
+    }
+
+    #[test]
+    fn test_parse_config_init_with_force() {
+        use crate::commands::config::ConfigCommands;
+
+        let cli =
+            Cli::try_parse_from(&["c", "config", "init", "--force"]).expect("Failed to parse");
+        match cli.command {
+            Some(Commands::Config(args)) => {
+                assert!(matches!(args.command, ConfigCommands::Init { force: true }));
+            }
+            _ => panic!("Expected Config command"),
+        }
+    }
+
     #[test]
     fn test_parse_history_subcommand() {
         let cli = Cli::try_parse_from(&["c", "history"]).expect("Failed to parse");
@@ -394,6 +661,280 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_parse_chat_subcommand_with_check_backend() {
+        let cli =
+            Cli::try_parse_from(&["c", "chat", "--check-backend", "-i"]).expect("Failed to parse");
+        if let Some(Commands::Chat(args)) = cli.command {
+            assert!(args.check_backend);
+            assert!(args.interactive);
+        } else {
+            panic!("Expected Chat command");
+        }
+    }
+
+    #[test]
+    fn test_parse_chat_subcommand_with_precheck() {
+        let cli = Cli::try_parse_from(&["c", "chat", "--precheck", "--timeout", "1.5", "hi"])
+            .expect("Failed to parse");
+        if let Some(Commands::Chat(args)) = cli.command {
+            assert!(args.precheck);
+            assert_eq!(args.timeout, Some(std::time::Duration::from_millis(1500)));
+            assert_eq!(args.query, vec!["hi"]);
+        } else {
+            panic!("Expected Chat command");
+        }
+
+        assert!(Cli::try_parse_from(&["c", "chat", "--timeout", "0", "hi"]).is_err());
+    }
+
+    #[test]
+    fn test_raw_flag_routes_to_chat() {
+        let args = args_vec(&["c", "--raw", "--", "session", "--name", "foo"]);
+        assert!(should_route_to_chat(&args));
+    }
+
+    #[test]
+    fn test_parse_raw_passes_goose_args_verbatim() {
+        let cli = Cli::try_parse_from(&["c", "chat", "--raw", "--", "session", "--name", "foo"])
+            .expect("Failed to parse");
+        if let Some(Commands::Chat(args)) = cli.command {
+            assert!(args.raw);
+            assert_eq!(args.query, vec!["session", "--name", "foo"]);
+        } else {
+            panic!("Expected Chat command");
+        }
+    }
+
+    #[test]
+    fn test_parse_raw_conflicts_with_interactive() {
+        let result = Cli::try_parse_from(&["c", "chat", "--raw", "-i"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_parse_interactive_with_session() {
+        let cli =
+            Cli::try_parse_from(&["c", "chat", "-i", "--session", "foo"]).expect("Failed to parse");
+        if let Some(Commands::Chat(args)) = cli.command {
+            assert!(args.interactive);
+            assert_eq!(args.session.as_deref(), Some("foo"));
+            assert!(!args.resume);
+        } else {
+            panic!("Expected Chat command");
+        }
+    }
+
+    #[test]
+    fn test_parse_session_requires_interactive() {
+        assert!(Cli::try_parse_from(&["c", "chat", "--session", "foo", "hello"]).is_err());
+        assert!(Cli::try_parse_from(&["c", "chat", "--resume", "hello"]).is_err());
+        assert!(Cli::try_parse_from(&["c", "chat", "--session", "foo"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_json_output_flag() {
+        let cli = Cli::try_parse_from(&["c", "chat", "--json", "hello"]).expect("Failed to parse");
+        if let Some(Commands::Chat(args)) = cli.command {
+            assert!(args.json);
+            assert_eq!(args.query, vec!["hello"]);
+        } else {
+            panic!("Expected Chat command");
+        }
+
+        assert!(Cli::try_parse_from(&["c", "chat", "--json", "-i"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_explain_flag() {
+        let args = vec![
+            "c".to_string(),
+            "--explain".to_string(),
+            "restart".to_string(),
+        ];
+        assert!(should_route_to_chat(&args));
+
+        let cli = Cli::try_parse_from(&["c", "chat", "--explain", "restart", "httpd"])
+            .expect("Failed to parse");
+        if let Some(Commands::Chat(args)) = cli.command {
+            assert!(args.explain);
+            assert_eq!(args.query, vec!["restart", "httpd"]);
+        } else {
+            panic!("Expected Chat command");
+        }
+
+        assert!(Cli::try_parse_from(&["c", "chat", "--explain", "-i"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_context_flags() {
+        let cli = Cli::try_parse_from(&["c", "chat", "--context", "which", "kernel"])
+            .expect("Failed to parse");
+        if let Some(Commands::Chat(args)) = cli.command {
+            assert!(args.context);
+            assert!(!args.no_context);
+        } else {
+            panic!("Expected Chat command");
+        }
+
+        assert!(Cli::try_parse_from(&["c", "chat", "--no-context", "hi"]).is_ok());
+        assert!(Cli::try_parse_from(&["c", "chat", "--context", "--no-context", "hi"]).is_err());
+        assert!(Cli::try_parse_from(&["c", "chat", "--context", "-i"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_attach_flag() {
+        let args = args_vec(&["c", "--attach", "main.rs", "what's wrong here"]);
+        assert!(should_route_to_chat(&args));
+
+        let cli = Cli::try_parse_from(&[
+            "c",
+            "chat",
+            "--attach",
+            "main.rs",
+            "--attach",
+            "lib.rs",
+            "what's wrong here",
+        ])
+        .expect("Failed to parse");
+        if let Some(Commands::Chat(args)) = cli.command {
+            assert_eq!(
+                args.attach,
+                [
+                    std::path::PathBuf::from("main.rs"),
+                    std::path::PathBuf::from("lib.rs")
+                ]
+            );
+            assert_eq!(args.query, vec!["what's wrong here"]);
+        } else {
+            panic!("Expected Chat command");
+        }
+
+        assert!(Cli::try_parse_from(&["c", "chat", "--attach", "main.rs", "-i"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_continue_flag() {
+        let args = args_vec(&["c", "--continue", "and on RHEL 8?"]);
+        assert!(should_route_to_chat(&args));
+
+        let cli = Cli::try_parse_from(&["c", "chat", "--continue", "and on RHEL 8?"])
+            .expect("Failed to parse");
+        if let Some(Commands::Chat(args)) = cli.command {
+            assert!(args.continue_conversation);
+            assert_eq!(args.query, vec!["and on RHEL 8?"]);
+        } else {
+            panic!("Expected Chat command");
+        }
+
+        assert!(Cli::try_parse_from(&["c", "chat", "--continue", "-i"]).is_err());
+        assert!(Cli::try_parse_from(&["c", "chat", "--continue", "--raw", "--", "run"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_repeat_last_flag() {
+        let args = args_vec(&["c", "--repeat-last"]);
+        assert!(should_route_to_chat(&args));
+
+        let cli = Cli::try_parse_from(&["c", "chat", "--repeat-last", "--editor"])
+            .expect("Failed to parse");
+        if let Some(Commands::Chat(args)) = cli.command {
+            assert!(args.repeat_last);
+            assert!(args.editor);
+            assert!(args.query.is_empty());
+        } else {
+            panic!("Expected Chat command");
+        }
+
+        assert!(Cli::try_parse_from(&["c", "chat", "--repeat-last", "hello"]).is_err());
+        assert!(Cli::try_parse_from(&["c", "chat", "--repeat-last", "-i"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_editor_flag() {
+        let args = args_vec(&["c", "--editor"]);
+        assert!(should_route_to_chat(&args));
+
+        let cli =
+            Cli::try_parse_from(&["c", "chat", "--editor", "--explain"]).expect("Failed to parse");
+        if let Some(Commands::Chat(args)) = cli.command {
+            assert!(args.editor);
+            assert!(args.query.is_empty());
+        } else {
+            panic!("Expected Chat command");
+        }
+
+        assert!(Cli::try_parse_from(&["c", "chat", "--editor", "hello"]).is_err());
+        assert!(Cli::try_parse_from(&["c", "chat", "--editor", "-i"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_safe_flag() {
+        let args = args_vec(&["c", "--safe", "how do I delete all logs"]);
+        assert!(should_route_to_chat(&args));
+
+        let cli = Cli::try_parse_from(&["c", "chat", "--safe", "how do I delete all logs"])
+            .expect("Failed to parse");
+        if let Some(Commands::Chat(args)) = cli.command {
+            assert!(args.safe);
+            assert_eq!(args.query, vec!["how do I delete all logs"]);
+        } else {
+            panic!("Expected Chat command");
+        }
+
+        assert!(Cli::try_parse_from(&["c", "chat", "--safe", "-i"]).is_err());
+        assert!(Cli::try_parse_from(&["c", "chat", "--safe", "--json", "hello"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_format_option() {
+        let cli = Cli::try_parse_from(&["c", "chat", "hello"]).expect("Failed to parse");
+        if let Some(Commands::Chat(args)) = cli.command {
+            assert_eq!(args.format, OutputFormat::Plain);
+        } else {
+            panic!("Expected Chat command");
+        }
+
+        let cli = Cli::try_parse_from(&["c", "chat", "--format", "markdown", "hello"])
+            .expect("Failed to parse");
+        if let Some(Commands::Chat(args)) = cli.command {
+            assert_eq!(args.format, OutputFormat::Markdown);
+            assert_eq!(args.query, vec!["hello"]);
+        } else {
+            panic!("Expected Chat command");
+        }
+
+        assert!(Cli::try_parse_from(&["c", "chat", "--format", "html", "hello"]).is_err());
+        assert!(Cli::try_parse_from(&["c", "chat", "--format", "markdown", "-i"]).is_err());
+        assert!(
+            Cli::try_parse_from(&["c", "chat", "--format", "markdown", "--json", "hi"]).is_err()
+        );
+    }
+
+    #[test]
+    fn test_parse_output_option() {
+        let cli = Cli::try_parse_from(&[
+            "c",
+            "chat",
+            "--output",
+            "nginx.service",
+            "--append",
+            "generate a systemd unit for nginx",
+        ])
+        .expect("Failed to parse");
+        if let Some(Commands::Chat(args)) = cli.command {
+            assert_eq!(args.output, Some(std::path::PathBuf::from("nginx.service")));
+            assert!(args.append);
+            assert_eq!(args.query, vec!["generate a systemd unit for nginx"]);
+        } else {
+            panic!("Expected Chat command");
+        }
+
+        assert!(Cli::try_parse_from(&["c", "chat", "--output", "a.txt", "-i"]).is_err());
+        assert!(Cli::try_parse_from(&["c", "chat", "--output", "a.txt", "--json", "hi"]).is_err());
+        assert!(Cli::try_parse_from(&["c", "chat", "--append", "hello"]).is_err());
+    }
+
     #[test]
     fn test_parse_no_subcommand() {
         let cli = Cli::try_parse_from(&["c"]).expect("Failed to parse");
@@ -462,11 +1003,11 @@ mod tests {
     }
 
     #[test]
-    fn test_history_with_verbose_flag_parses() {
-        let cli = Cli::try_parse_from(&["c", "history", "--verbose"]).expect("Failed to parse");
+    fn test_history_with_full_flag_parses() {
+        let cli = Cli::try_parse_from(&["c", "history", "--full"]).expect("Failed to parse");
         match cli.command {
             Some(Commands::History(args)) => {
-                assert!(args.verbose, "Expected verbose flag to be true");
+                assert!(args.full, "Expected full flag to be true");
             }
             _ => panic!("Expected History command"),
         }
@@ -0,0 +1,141 @@
+//! Terminal rendering of markdown answers, for `--format markdown`
+//!
+//! Only the markdown the assistant commonly writes is handled: headers,
+//! bullet lists, fenced code blocks, `**bold**` and `` `code` `` spans.
+//! Anything else, such as a lone `*` in `rm *.log`, is printed as it is.
+//! Styles go through the [`Palette`], so without color the markers are
+//! still removed but no escape sequences are written.
+
+use crate::color::{Palette, Style};
+
+/// Indentation of the lines of a fenced code block
+const CODE_INDENT: &str = "    ";
+
+/// Render `text` for the terminal
+pub fn render(text: &str, palette: Palette) -> String {
+    let mut rendered = String::with_capacity(text.len());
+    let mut in_code_block = false;
+    for line in text.split_inclusive('\n') {
+        let (content, newline) = match line.strip_suffix('\n') {
+            Some(content) => (content, "\n"),
+            None => (line, ""),
+        };
+
+        if content.trim_start().starts_with("```") {
+            // The fences themselves are not shown
+            in_code_block = !in_code_block;
+            continue;
+        }
+        if in_code_block {
+            rendered.push_str(CODE_INDENT);
+            rendered.push_str(&palette.paint(content, Style::Cyan));
+        } else {
+            rendered.push_str(&render_line(content, palette));
+        }
+        rendered.push_str(newline);
+    }
+    rendered
+}
+
+/// Render one line outside code blocks
+fn render_line(line: &str, palette: Palette) -> String {
+    let trimmed = line.trim_start();
+    let indent = &line[..line.len() - trimmed.len()];
+
+    if let Some(title) = header_text(trimmed) {
+        return format!("{}{}", indent, palette.paint(title, Style::Bold));
+    }
+    for bullet in ["- ", "* ", "+ "] {
+        if let Some(item) = trimmed.strip_prefix(bullet) {
+            return format!("{}• {}", indent, render_inline(item, palette));
+        }
+    }
+    format!("{}{}", indent, render_inline(trimmed, palette))
+}
+
+/// Text of an ATX header such as `## Steps`
+fn header_text(line: &str) -> Option<&str> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+    let title = line[level..].strip_prefix(' ')?;
+    Some(title.trim().trim_end_matches('#').trim_end())
+}
+
+/// Render the `**bold**` and `` `code` `` spans of `text`
+///
+/// A marker without a closing one on the same line is kept as it is.
+fn render_inline(text: &str, palette: Palette) -> String {
+    let mut rendered = String::with_capacity(text.len());
+    let mut rest = text;
+    while !rest.is_empty() {
+        let span = [("`", Style::Cyan), ("**", Style::Bold)]
+            .into_iter()
+            .find_map(|(marker, style)| {
+                let inner = rest.strip_prefix(marker)?;
+                let end = inner.find(marker).filter(|end| *end > 0)?;
+                Some((&inner[..end], marker.len() * 2 + end, style))
+            });
+        match span {
+            Some((inner, length, style)) => {
+                rendered.push_str(&palette.paint(inner, style));
+                rest = &rest[length..];
+            }
+            None => {
+                let c = rest.chars().next().unwrap_or_default();
+                rendered.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    rendered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_headers_and_bullets() {
+        let text = "## Steps ##\n- install it\n  * then **enable** it\n";
+
+        assert_eq!(
+            render(text, Palette::PLAIN),
+            "Steps\n• install it\n  • then enable it\n"
+        );
+    }
+
+    #[test]
+    fn test_render_code_block_keeps_its_lines() {
+        let text = "Run:\n\n```bash\nfor f in *.log; do\n  gzip  \"$f\"\ndone\n```\nDone.";
+
+        assert_eq!(
+            render(text, Palette::PLAIN),
+            "Run:\n\n    for f in *.log; do\n      gzip  \"$f\"\n    done\nDone."
+        );
+    }
+
+    #[test]
+    fn test_render_inline_spans() {
+        assert_eq!(
+            render_inline("use `dnf **update**` as **root**", Palette::PLAIN),
+            "use dnf **update** as root"
+        );
+    }
+
+    #[test]
+    fn test_render_keeps_unmatched_markers() {
+        for text in [
+            "rm *.log",
+            "2 ** 8",
+            "a ` b",
+            "****",
+            "#hashtag",
+            "x * y * z",
+            "__init__",
+        ] {
+            assert_eq!(render(text, Palette::PLAIN), text);
+        }
+    }
+}
@@ -0,0 +1,23 @@
+//! Wrapper messages on stderr
+//!
+//! Errors are always printed, since they explain the exit code. Advisory
+//! lines that only help a human (installation hints, backend warnings) go
+//! through [`advise`] and are dropped with `--quiet`.
+
+use std::fmt::Display;
+use std::sync::OnceLock;
+
+/// Whether `--quiet` was given
+static QUIET: OnceLock<bool> = OnceLock::new();
+
+/// Record the `--quiet` flag for this process
+pub fn init(quiet: bool) {
+    let _ = QUIET.set(quiet);
+}
+
+/// Print an advisory message on stderr, unless `--quiet` was given
+pub fn advise(message: impl Display) {
+    if !QUIET.get().copied().unwrap_or(false) {
+        eprintln!("{}", message);
+    }
+}
@@ -0,0 +1,156 @@
+//! Warnings about destructive commands in answers, for `--safe`
+//!
+//! With `--safe`, the goose output is captured and scanned before it is
+//! shown, and a warning is printed around it when a line matches one of the
+//! patterns, so a suggested `rm -rf` doesn't get pasted without a second
+//! look. The patterns are regular expressions, taken from `safe_patterns` in
+//! cli.toml or [`DEFAULT_PATTERNS`].
+
+use anyhow::{Context, Result};
+use regex::Regex;
+
+use crate::color::{Palette, Style};
+
+/// Patterns used when cli.toml sets no `safe_patterns`
+pub const DEFAULT_PATTERNS: &[&str] = &[
+    // rm with -r, -R or -f, alone or among other flags
+    r"\brm\s+(-\S+\s+)*(-[a-zA-Z]*[rRf][a-zA-Z]*|--recursive|--force)\b",
+    r"\bmkfs(\.\w+)?\b",
+    r"\bdd\b.*\bof=",
+    r"\b(shred|wipefs)\b",
+    r"\b(fdisk|sfdisk|parted)\s+/dev/",
+    r">\s*/dev/(sd|hd|vd|xvd|nvme|mmcblk)",
+    r"\bchmod\s+(-R\s+)?(0?777|a\+rwx)\s+/",
+    r":\(\)\s*\{\s*:\s*\|\s*:\s*&\s*\}\s*;\s*:",
+];
+
+/// Compiled patterns of commands that deserve a warning
+#[derive(Debug)]
+pub struct DangerousPatterns {
+    patterns: Vec<Regex>,
+}
+
+impl DangerousPatterns {
+    /// Compile `patterns`, failing on the first invalid one
+    pub fn new<S: AsRef<str>>(patterns: &[S]) -> Result<Self> {
+        let patterns = patterns
+            .iter()
+            .map(|pattern| {
+                let pattern = pattern.as_ref();
+                Regex::new(pattern)
+                    .with_context(|| format!("Invalid safe_patterns entry {:?}", pattern))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { patterns })
+    }
+
+    /// Patterns from cli.toml, or the defaults when it sets none
+    pub fn from_config(patterns: Option<&[String]>) -> Result<Self> {
+        match patterns {
+            Some(patterns) => Self::new(patterns),
+            None => Self::new(DEFAULT_PATTERNS),
+        }
+    }
+
+    /// Lines of `text` matching a pattern, trimmed, in order
+    pub fn find<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        text.lines()
+            .filter(|line| self.patterns.iter().any(|pattern| pattern.is_match(line)))
+            .map(str::trim)
+            .collect()
+    }
+}
+
+/// Warning printed before an answer with the matching `lines`
+pub fn warning_banner(lines: &[&str], palette: Palette) -> String {
+    let mut banner = palette.paint(
+        "WARNING: this answer contains potentially destructive commands:",
+        Style::Red,
+    );
+    for line in lines {
+        banner.push_str("\n  ");
+        banner.push_str(line);
+    }
+    banner
+}
+
+/// Reminder printed after an answer the banner was printed for
+pub fn warning_reminder(palette: Palette) -> String {
+    palette.paint(
+        "WARNING: review the commands flagged above before running them.",
+        Style::Red,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_patterns_flag_destructive_commands() {
+        let patterns = DangerousPatterns::from_config(None).unwrap();
+
+        for line in [
+            "sudo rm -rf /var/log/*",
+            "rm -v -r old-logs",
+            "rm --force /var/log/messages",
+            "mkfs.ext4 /dev/sdb1",
+            "dd if=/dev/zero of=/dev/sda bs=1M",
+            "shred -u secrets.txt",
+            "wipefs -a /dev/sdb",
+            "parted /dev/sda mklabel gpt",
+            "cat image > /dev/nvme0n1",
+            "chmod -R 777 /",
+            ":(){ :|:& };:",
+        ] {
+            assert_eq!(patterns.find(line), [line], "{:?}", line);
+        }
+
+        for line in [
+            "ls -la /var/log",
+            "rm notes.txt",
+            "journalctl --vacuum-size=100M",
+            "perform -rf checks",
+            "echo done > /dev/null",
+        ] {
+            assert!(patterns.find(line).is_empty(), "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn test_find_returns_matching_lines() {
+        let patterns = DangerousPatterns::from_config(None).unwrap();
+        let answer = "To free space, remove old logs:\n\n    sudo rm -rf /var/log/*.gz\n\nThen check with df -h.\n";
+
+        assert_eq!(patterns.find(answer), ["sudo rm -rf /var/log/*.gz"]);
+    }
+
+    #[test]
+    fn test_configured_patterns_replace_defaults() {
+        let configured = vec![r"\bsystemctl\s+stop\b".to_string()];
+        let patterns = DangerousPatterns::from_config(Some(&configured)).unwrap();
+
+        assert_eq!(
+            patterns.find("systemctl stop sshd"),
+            ["systemctl stop sshd"]
+        );
+        assert!(patterns.find("rm -rf /tmp/x").is_empty());
+    }
+
+    #[test]
+    fn test_invalid_pattern_is_an_error() {
+        let err = DangerousPatterns::new(&["rm (-rf"]).unwrap_err();
+
+        assert!(format!("{:#}", err).contains("Invalid safe_patterns entry"));
+    }
+
+    #[test]
+    fn test_warning_banner_lists_lines() {
+        let banner = warning_banner(&["rm -rf /tmp/x", "mkfs /dev/sdb"], Palette::PLAIN);
+
+        assert_eq!(
+            banner,
+            "WARNING: this answer contains potentially destructive commands:\n  rm -rf /tmp/x\n  mkfs /dev/sdb"
+        );
+    }
+}
@@ -0,0 +1,207 @@
+//! Last conversation, resumed by `--continue`, and last query, asked again
+//! by `--repeat-last`
+//!
+//! Plain runs leave goose to name their session. Once goose is done, the
+//! session it wrote to most recently is looked up in its sessions directory
+//! and its name is kept in the user data directory, so that a later query
+//! can resume it. The text of the most recent query is kept next to it.
+
+use anyhow::{Context, Result};
+use etcetera::{choose_base_strategy, BaseStrategy};
+use log::debug;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::helpers::{atomic_write, validate_session_name};
+
+/// Directory of the CLI's own data, inside the user data directory
+const CLI_DATA_DIR: &str = "command-line-assistant";
+
+/// File holding the name of the most recent session, inside [`CLI_DATA_DIR`]
+const LAST_SESSION_FILE: &str = "last_session";
+
+/// File holding the text of the most recent query, inside [`CLI_DATA_DIR`]
+const LAST_QUERY_FILE: &str = "last_query";
+
+/// Goose's sessions directory, inside the user data directory
+const GOOSE_SESSIONS_DIR: &str = "goose/sessions";
+
+/// Extension of the session files goose writes
+const GOOSE_SESSION_EXTENSION: &str = "jsonl";
+
+/// The user data directory
+fn data_dir() -> Result<PathBuf> {
+    let base = choose_base_strategy()
+        .context("Failed to determine data directory (HOME environment variable may not be set)")?;
+    Ok(base.data_dir())
+}
+
+/// Path of the file recording the most recent session
+pub fn last_session_path() -> Result<PathBuf> {
+    Ok(data_dir()?.join(CLI_DATA_DIR).join(LAST_SESSION_FILE))
+}
+
+/// Path of the file recording the most recent query
+pub fn last_query_path() -> Result<PathBuf> {
+    Ok(data_dir()?.join(CLI_DATA_DIR).join(LAST_QUERY_FILE))
+}
+
+/// Directory goose keeps its sessions in
+pub fn goose_sessions_dir() -> Result<PathBuf> {
+    Ok(data_dir()?.join(GOOSE_SESSIONS_DIR))
+}
+
+/// Name of the session goose wrote to most recently in `dir`, if it did so
+/// at or after `since`
+///
+/// Sessions last written before `since` belong to earlier runs and are
+/// ignored, as are files whose name goose would refuse.
+pub fn newest_goose_session(dir: &Path, since: SystemTime) -> Option<String> {
+    let mut newest: Option<(SystemTime, String)> = None;
+    for entry in fs::read_dir(dir).ok()?.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some(GOOSE_SESSION_EXTENSION) {
+            continue;
+        }
+        let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+            continue;
+        };
+        let Ok(modified) = entry.metadata().and_then(|metadata| metadata.modified()) else {
+            continue;
+        };
+        if modified < since || validate_session_name(name).is_err() {
+            continue;
+        }
+        if newest.as_ref().is_none_or(|(time, _)| modified > *time) {
+            newest = Some((modified, name.to_string()));
+        }
+    }
+    newest.map(|(_, name)| name)
+}
+
+/// Name of the most recent session recorded at `path`
+///
+/// A missing file or a name goose would refuse is treated as no session.
+pub fn read_last_session(path: &Path) -> Option<String> {
+    let content = fs::read_to_string(path).ok()?;
+    let name = content.trim();
+    match validate_session_name(name) {
+        Ok(()) => Some(name.to_string()),
+        Err(e) => {
+            debug!("Ignoring recorded session {:?}: {:#}", name, e);
+            None
+        }
+    }
+}
+
+/// Record `name` at `path` as the most recent session
+pub fn record_last_session(path: &Path, name: &str) -> Result<()> {
+    record(path, &format!("{}\n", name))
+}
+
+/// Text of the most recent query recorded at `path`
+///
+/// A missing or blank file is treated as no query.
+pub fn read_last_query(path: &Path) -> Option<String> {
+    let content = fs::read_to_string(path).ok()?;
+    let query = content.strip_suffix('\n').unwrap_or(&content);
+    (!query.trim().is_empty()).then(|| query.to_string())
+}
+
+/// Record `query` at `path` as the most recent query
+pub fn record_last_query(path: &Path, query: &str) -> Result<()> {
+    record(path, &format!("{}\n", query))
+}
+
+/// Replace the file at `path` with `content`, creating its directory
+fn record(path: &Path, content: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory {:?}", parent))?;
+    }
+    atomic_write(path, content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_record_and_read_last_session() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("data").join(LAST_SESSION_FILE);
+
+        assert_eq!(read_last_session(&path), None);
+
+        record_last_session(&path, "httpd-debug").unwrap();
+        assert_eq!(read_last_session(&path).as_deref(), Some("httpd-debug"));
+
+        record_last_session(&path, "20250101_1").unwrap();
+        assert_eq!(read_last_session(&path).as_deref(), Some("20250101_1"));
+    }
+
+    #[test]
+    fn test_read_last_session_ignores_invalid_names() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(LAST_SESSION_FILE);
+
+        for content in ["", "\n", "../escape", "--help"] {
+            fs::write(&path, content).unwrap();
+            assert_eq!(read_last_session(&path), None, "{:?}", content);
+        }
+    }
+
+    #[test]
+    fn test_newest_goose_session() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        let write = |name: &str, age: u64| {
+            let file = fs::File::create(dir.join(name)).unwrap();
+            file.set_modified(SystemTime::now() - Duration::from_secs(age))
+                .unwrap();
+        };
+        let since = SystemTime::now() - Duration::from_secs(60);
+
+        assert_eq!(newest_goose_session(&dir.join("missing"), since), None);
+
+        // Only sessions written since the run started count
+        write("20250101_1.jsonl", 3600);
+        assert_eq!(newest_goose_session(dir, since), None);
+
+        write("20250101_2.jsonl", 20);
+        write("20250101_3.jsonl", 10);
+        write("notes.txt", 0);
+        write("-rf.jsonl", 0);
+        assert_eq!(
+            newest_goose_session(dir, since).as_deref(),
+            Some("20250101_3")
+        );
+    }
+
+    #[test]
+    fn test_record_and_read_last_query() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("data").join(LAST_QUERY_FILE);
+
+        assert_eq!(read_last_query(&path), None);
+
+        record_last_query(&path, "how do I list open ports?").unwrap();
+        assert_eq!(
+            read_last_query(&path).as_deref(),
+            Some("how do I list open ports?")
+        );
+
+        // Queries written in the editor keep their lines
+        record_last_query(&path, "why does this fail:\n\n  dnf update\n").unwrap();
+        assert_eq!(
+            read_last_query(&path).as_deref(),
+            Some("why does this fail:\n\n  dnf update\n")
+        );
+
+        fs::write(&path, " \n").unwrap();
+        assert_eq!(read_last_query(&path), None);
+    }
+}
@@ -0,0 +1,232 @@
+//! Numbered steps for `--explain` answers
+//!
+//! The model is asked for numbered steps, but doesn't always write them:
+//! lists come back with bullets, with numbers that skip, or as plain
+//! paragraphs. [`number_steps`] rewrites the answer so every step is
+//! numbered in order. When the answer has list items, each item is a step
+//! and text around the list is kept as it is; otherwise each paragraph is
+//! a step. Fenced code blocks and indented lines stay with the step before
+//! them.
+
+/// Indentation of the lines that continue a step
+const STEP_INDENT: &str = "   ";
+
+/// A part of the rewritten answer
+#[derive(Debug)]
+enum Block {
+    /// Lines kept as they are, outside any step
+    Text(Vec<String>),
+    /// The lines of one step, the first one without its list marker
+    Step(Vec<String>),
+}
+
+/// Rewrite `text` as numbered steps
+pub fn number_steps(text: &str) -> String {
+    let lines = classify(text);
+    let has_items = lines.iter().any(|line| matches!(line, Line::Item(_)));
+
+    let mut blocks: Vec<Block> = Vec::new();
+    let mut after_blank = true;
+    for line in lines {
+        match line {
+            Line::Blank => {
+                after_blank = true;
+                continue;
+            }
+            Line::Item(item) => blocks.push(Block::Step(vec![item])),
+            Line::Code(code) => match blocks.last_mut() {
+                Some(Block::Step(step) | Block::Text(step)) => step.push(code),
+                None if has_items => blocks.push(Block::Text(vec![code])),
+                None => blocks.push(Block::Step(vec![code])),
+            },
+            Line::Indented(text) => match blocks.last_mut() {
+                Some(Block::Step(step) | Block::Text(step)) => step.push(text),
+                None => blocks.push(Block::Text(vec![text])),
+            },
+            Line::Plain(text) => match blocks.last_mut() {
+                Some(Block::Step(step) | Block::Text(step)) if !after_blank => step.push(text),
+                _ if has_items => blocks.push(Block::Text(vec![text])),
+                _ => blocks.push(Block::Step(vec![text])),
+            },
+        }
+        after_blank = false;
+    }
+
+    render(&blocks)
+}
+
+/// One line of the answer, by what it means for the steps
+#[derive(Debug)]
+enum Line {
+    /// An empty line
+    Blank,
+    /// A list item, with its marker removed
+    Item(String),
+    /// A line of a fenced code block, fences included
+    Code(String),
+    /// A line starting with whitespace, trimmed
+    Indented(String),
+    /// Any other line
+    Plain(String),
+}
+
+/// Sort the lines of `text`
+fn classify(text: &str) -> Vec<Line> {
+    let mut lines = Vec::new();
+    let mut in_code_block = false;
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") {
+            in_code_block = !in_code_block;
+            lines.push(Line::Code(trimmed.to_string()));
+        } else if in_code_block {
+            lines.push(Line::Code(line.to_string()));
+        } else if trimmed.is_empty() {
+            lines.push(Line::Blank);
+        } else if let Some(item) = list_item(line) {
+            lines.push(Line::Item(item.to_string()));
+        } else if line.starts_with(char::is_whitespace) {
+            lines.push(Line::Indented(trimmed.to_string()));
+        } else {
+            lines.push(Line::Plain(trimmed.to_string()));
+        }
+    }
+    lines
+}
+
+/// Text of a top-level list item such as `3. Restart`, `2) Restart`,
+/// `- Restart` or `Step 4: Restart`
+fn list_item(line: &str) -> Option<&str> {
+    if line.starts_with(char::is_whitespace) {
+        return None;
+    }
+    for bullet in ["- ", "* ", "+ "] {
+        if let Some(item) = line.strip_prefix(bullet) {
+            return Some(item.trim());
+        }
+    }
+
+    let rest = line
+        .strip_prefix("Step ")
+        .or_else(|| line.strip_prefix("step "))
+        .unwrap_or(line);
+    let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    if digits == 0 {
+        return None;
+    }
+    let item = [". ", ") ", ": "]
+        .iter()
+        .find_map(|separator| rest[digits..].strip_prefix(separator))?;
+    Some(item.trim())
+}
+
+/// Write `blocks` out, numbering the steps from 1
+fn render(blocks: &[Block]) -> String {
+    let mut rendered = Vec::new();
+    let mut number = 0;
+    for block in blocks {
+        match block {
+            Block::Text(lines) => {
+                if !rendered.is_empty() {
+                    rendered.push(String::new());
+                }
+                rendered.extend(lines.iter().cloned());
+                rendered.push(String::new());
+            }
+            Block::Step(lines) => {
+                number += 1;
+                let mut lines = lines.iter();
+                if let Some(first) = lines.next() {
+                    rendered.push(format!("{}. {}", number, first));
+                }
+                rendered.extend(lines.map(|line| format!("{}{}", STEP_INDENT, line)));
+            }
+        }
+    }
+    while rendered.last().is_some_and(String::is_empty) {
+        rendered.pop();
+    }
+
+    let mut text = rendered.join("\n");
+    text.push('\n');
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_renumbers_list_items() {
+        let answer = "To free up disk space:\n\
+                      1. Find large files\n\
+                      3) Remove old logs\n\
+                      - Clean the package cache\n\
+                      Step 7: Check the result\n";
+
+        assert_eq!(
+            number_steps(answer),
+            "To free up disk space:\n\
+             \n\
+             1. Find large files\n\
+             2. Remove old logs\n\
+             3. Clean the package cache\n\
+             4. Check the result\n"
+        );
+    }
+
+    #[test]
+    fn test_commands_stay_with_their_step() {
+        let answer = "1. Check the service:\n\
+                      ```bash\n\
+                      systemctl status nginx\n\
+                      ```\n\
+                      2. Restart it:\n    \
+                      sudo systemctl restart nginx\n\
+                      \n\
+                      That's all.\n";
+
+        assert_eq!(
+            number_steps(answer),
+            "1. Check the service:\n   \
+             ```bash\n   \
+             systemctl status nginx\n   \
+             ```\n\
+             2. Restart it:\n   \
+             sudo systemctl restart nginx\n\
+             \n\
+             That's all.\n"
+        );
+    }
+
+    #[test]
+    fn test_paragraphs_become_steps() {
+        let answer = "Open the firewall port.\n\
+                      \n\
+                      Reload the firewall so\n\
+                      the rule applies.\n\
+                      ```\n\
+                      firewall-cmd --reload\n\
+                      ```\n";
+
+        assert_eq!(
+            number_steps(answer),
+            "1. Open the firewall port.\n\
+             2. Reload the firewall so\n   \
+             the rule applies.\n   \
+             ```\n   \
+             firewall-cmd --reload\n   \
+             ```\n"
+        );
+    }
+
+    #[test]
+    fn test_list_item() {
+        assert_eq!(list_item("12. Reboot"), Some("Reboot"));
+        assert_eq!(list_item("step 2: Reboot"), Some("Reboot"));
+        assert_eq!(list_item("* Reboot"), Some("Reboot"));
+        assert_eq!(list_item("  - nested"), None);
+        assert_eq!(list_item("2024 was a year"), None);
+        assert_eq!(list_item("rm *.log"), None);
+    }
+}
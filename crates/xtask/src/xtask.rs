@@ -74,7 +74,7 @@ fn try_main() -> Result<()> {
             .iter()
             .find_map(|(k, f)| (*k == cmd).then_some(*f))
             .unwrap_or(print_help);
-        return f(&sh);
+        f(&sh)
     } else {
         print_help(&sh)?;
         Ok(())